@@ -1,23 +1,21 @@
 
 // https://adventofcode.com/2022/day/1
 
-use std::{fs::File, io::{BufReader, Lines}};
-
-use util::advent_of_code::redirect;
+use util::{advent_of_code::redirect, files::InputLines};
 
 fn main() {
     redirect(part_1, part_2);
 }
 
 
-pub fn part_1(lines: Lines<BufReader<File>>) {
+pub fn part_1(lines: InputLines) {
     match parse_elves(lines).iter().max_by(|a, b| b.1.cmp(&a.1)) {
         Some(elf) => println!("{}", elf.1),
         None => println!("no elves"),
     }
 }
 
-pub fn part_2(lines: Lines<BufReader<File>>) {
+pub fn part_2(lines: InputLines) {
     let top_elves = 3;
 
     let mut elves = parse_elves(lines);
@@ -34,7 +32,7 @@ pub fn part_2(lines: Lines<BufReader<File>>) {
 
 struct Elf(i32, i32);
 
-fn parse_elves(lines: Lines<BufReader<File>>) -> Vec<Elf> {
+fn parse_elves(lines: InputLines) -> Vec<Elf> {
     let mut elves:Vec<Elf> = Vec::new();
     
     let mut elf_n: i32 = 0;
@@ -1,17 +1,24 @@
 
 // https://adventofcode.com/2022/day/2
 
-use std::{fs::File, io::{BufReader, Lines}};
-
-use util::advent_of_code::redirect;
+use util::{advent_of_code::{solve, Solution}, files::Input};
 
 fn main() {
-    redirect(part_1, part_2);
+    solve::<Day2>();
 }
 
-pub fn part_1(lines: Lines<BufReader<File>>) {
-    let score = calc(lines, strategy_1);
-    println!("Total score: {}", score);
+struct Day2;
+
+impl Solution for Day2 {
+    fn part_1(input: &Input) {
+        let score = calc(input, strategy_1);
+        println!("Total score: {}", score);
+    }
+
+    fn part_2(input: &Input) {
+        let score = calc(input, strategy_2);
+        println!("Total score: {}", score);
+    }
 }
 
 fn strategy_1(str:&str, _:&RPS) -> RPS {
@@ -23,11 +30,6 @@ fn strategy_1(str:&str, _:&RPS) -> RPS {
     }
 }
 
-pub fn part_2(lines: Lines<BufReader<File>>) {
-    let score = calc(lines, strategy_2);
-    println!("Total score: {}", score);
-}
-
 fn strategy_2(str:&str, predicted:&RPS) -> RPS {
     match str {
         "X" => predicted.wins_against(),  // Outcome is a loss
@@ -39,10 +41,9 @@ fn strategy_2(str:&str, predicted:&RPS) -> RPS {
 
 
 
-fn calc(lines:Lines<BufReader<File>>, strategy: fn(&str, &RPS) -> RPS) -> i32 {
-    lines.flatten()
-         .into_iter()
-         .map(|line| Round::from_str(&line, strategy).calc_score())
+fn calc(input:&Input, strategy: fn(&str, &RPS) -> RPS) -> i32 {
+    input.lines()
+         .map(|line| Round::from_str(line, strategy).calc_score())
          .sum()
 }
 
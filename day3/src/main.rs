@@ -1,15 +1,13 @@
 
 // https://adventofcode.com/2022/day/3
 
-use std::{io::{BufReader, Lines}, fs::File};
-
-use util::advent_of_code::redirect;
+use util::{advent_of_code::redirect, files::InputLines};
 
 fn main() {
     redirect(part_1, part_2);
 }
 
-pub fn part_1(lines: Lines<BufReader<File>>) {
+pub fn part_1(lines: InputLines) {
     let priority_sum:i32 = lines.flatten()
                                 .map(|line| total_priority(&line))
                                 .sum();
@@ -17,7 +15,7 @@ pub fn part_1(lines: Lines<BufReader<File>>) {
     println!("Total sum: {}", priority_sum);
 }
 
-pub fn part_2(lines: Lines<BufReader<File>>) {
+pub fn part_2(lines: InputLines) {
     let n = 3;
     let mut sum_badges = 0;
     
@@ -1,25 +1,25 @@
 
 // https://adventofcode.com/2022/day/4
 
-use std::{io::{BufReader, Lines}, fs::File, str::FromStr};
+use std::str::FromStr;
 
-use util::advent_of_code::redirect;
+use util::{advent_of_code::redirect, files::InputLines};
 
 fn main() {
     redirect(part_1, part_2);
 }
 
-pub fn part_1 (lines: Lines<BufReader<File>>) {
+pub fn part_1 (lines: InputLines) {
     let result = calc(lines, Range::contained_in);
     println!("{result}");
 } 
 
-pub fn part_2 (lines: Lines<BufReader<File>>) {
+pub fn part_2 (lines: InputLines) {
     let result = calc(lines, Range::overlaps_with);
     println!("{result}");
 }
 
-pub fn calc(lines: Lines<BufReader<File>>, f:fn(&Range, &Range) -> bool) -> i32 {
+pub fn calc(lines: InputLines, f:fn(&Range, &Range) -> bool) -> i32 {
     let mut count = 0;
 
     for line in lines.flatten() {
@@ -1,23 +1,25 @@
 
 // https://adventofcode.com/2022/day/5
 
-use std::{fs::File, io::{BufReader, Lines}, usize};
+use std::usize;
 use regex::Regex;
-use util::advent_of_code::redirect;
+use util::{advent_of_code::redirect_with, files::{InputLines, NormalizeOptions}};
 
 fn main() {
-    redirect(part_1, part_2);
+    // the stacks drawing (first block) relies on its trailing spaces
+    let options = NormalizeOptions { fixed_width_blocks: vec![0] };
+    redirect_with(options, part_1, part_2);
 }
 
-pub fn part_1 (lines: Lines<BufReader<File>>) {
+pub fn part_1 (lines: InputLines) {
     calc(lines, crate_mover_9000_loader, crate_mover_9000_unloader)
 } 
 
-pub fn part_2 (lines: Lines<BufReader<File>>) {
+pub fn part_2 (lines: InputLines) {
     calc(lines, crate_mover_9001_loader, crate_mover_9001_unloader)
 } 
 
-fn calc( lines: Lines<BufReader<File>>
+fn calc( lines: InputLines
        , loader: fn (&mut Vec<char>, i32) -> Vec<char>
        , unloader: fn (&mut Vec<char>, Vec<char>)
        ) {
@@ -65,7 +67,7 @@ struct Move {
     to:usize,
 }
 
-fn parse_input(lines: Lines<BufReader<File>>) -> (Vec<Vec<char>>, Vec<Move>) {
+fn parse_input(lines: InputLines) -> (Vec<Vec<char>>, Vec<Move>) {
     
     // flatten out lines to avoid unwraps and such
     let mut lines = lines.flatten();
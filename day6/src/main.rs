@@ -1,23 +1,23 @@
 
 // https://adventofcode.com/2022/day/6
 
-use std::{collections::VecDeque, fs::File, io::{BufReader, Lines}, usize};
+use std::{collections::VecDeque, usize};
 
-use util::advent_of_code::redirect;
+use util::{advent_of_code::redirect, files::InputLines};
 
 fn main() {
     redirect(part_1, part_2);
 }
 
-pub fn part_1(lines: Lines<BufReader<File>>) {
+pub fn part_1(lines: InputLines) {
     calc(lines, 4)
 }
 
-pub fn part_2(lines: Lines<BufReader<File>>) {
+pub fn part_2(lines: InputLines) {
     calc(lines, 14)
 }
 
-fn calc(mut lines: Lines<BufReader<File>>, n: usize) {
+fn calc(mut lines: InputLines, n: usize) {
     let line = lines.next().unwrap().unwrap();
     let mut sequence = line.chars();
 
@@ -1,46 +1,52 @@
 
 // https://adventofcode.com/2022/day/7
 
-use std::{fs::File, io::{BufReader, Lines}};
+use std::io::{self, IsTerminal};
 
 use regex::Regex;
-use util::{addressable_tree::AddressableTree, advent_of_code::redirect, tree_navigator::TreeNavigator};
+use serde::Serialize;
+use shell::Shell;
+use util::{addressable_tree::AddressableTree, advent_of_code::redirect_with_commands, files::{read_normalized_lines, InputLines, NormalizeOptions}, tree_diff::TreeChange, tree_navigator::{Move, NavigationError, TreeNavigator}, tree_query::Segment, tree_render::RenderOptions, tree_view::TreeView};
+
+mod shell;
 
 fn main() {
-    redirect(part_1, part_2);
+    redirect_with_commands(NormalizeOptions::default(), part_1, part_2, &[
+        ("tree", print_tree),
+        ("dot", print_dot),
+        ("json", print_json),
+        ("diff", print_diff),
+        ("find", print_find),
+        ("trace", print_trace),
+        ("shell", run_shell),
+        ("du", print_du),
+    ]);
 }
 
 type FileSystem = AddressableTree<String, Item>;
 type Item = (FileType, i32);
 
+#[derive(Serialize, PartialEq, Clone, Debug)]
 enum FileType {
     File,
     Dir
 } 
 
-pub fn part_1(lines: Lines<BufReader<File>>) {
+pub fn part_1(lines: InputLines) {
     
     // parse input
-    let mut fs = parse_input(lines);
+    let mut fs = parse_input(lines, false);
     
     // recursively calculate file and dir sizes
     fs.map_values(calc_sizes); 
 
-    // gather all dirs with size under 100000
-    let dirs = fs.fold(&|fs, mut acc: Vec<Item>| {
-        if let (FileType::Dir, size) = fs.get_value() {
-            if *size <= 100000 {
-                acc.push((FileType::Dir, *size)); 
-            }
-        }
-        return acc;
-    }, Vec::new());
-
-    // sum all dir sizes
-    let mut sum_sizes = 0;
-    for dir in dirs {
-        sum_sizes += dir.1;
-    }
+    // sum all dirs with size under 100000
+    let sum_sizes: i32 = fs.pre_order()
+                           .filter_map(|(_, node)| match node.get_value() {
+                               (FileType::Dir, size) if *size <= 100000 => Some(*size),
+                               _ => None,
+                           })
+                           .sum();
 
     println!("Total sum size: {}", sum_sizes);
 }
@@ -60,9 +66,9 @@ fn calc_sizes(fs: &FileSystem) -> Item {
     }
 }
 
-pub fn part_2(lines: Lines<BufReader<File>>) {
+pub fn part_2(lines: InputLines) {
     // parse input
-    let mut fs = parse_input(lines);
+    let mut fs = parse_input(lines, false);
     
     // recursively calculate file and dir sizes
     fs.map_values(calc_sizes); 
@@ -85,47 +91,327 @@ pub fn part_2(lines: Lines<BufReader<File>>) {
     println!("Result: {}", dir.1);
 }
 
-fn parse_input(lines: Lines<BufReader<File>>) -> FileSystem {
-    // - first "cd /" is ignored
+// Prints the parsed filesystem, with optional --max-depth <n> and --hide-leaves
+pub fn print_tree(lines: InputLines, args: &[String]) {
+    let (lenient, args) = split_lenient(args);
+    let mut fs = parse_input(lines, lenient);
+    fs.map_values(calc_sizes);
+
+    print!("{}", fs.render(&render_options(&args)));
+}
+
+// Prints the parsed filesystem as a Graphviz graph, same options as print_tree
+pub fn print_dot(lines: InputLines, args: &[String]) {
+    let (lenient, args) = split_lenient(args);
+    let mut fs = parse_input(lines, lenient);
+    fs.map_values(calc_sizes);
+
+    print!("{}", fs.to_dot(&render_options(&args)));
+}
+
+// Dumps the reconstructed filesystem (file sizes only, dirs at 0) as JSON
+pub fn print_json(lines: InputLines, args: &[String]) {
+    let (lenient, _) = split_lenient(args);
+    let fs = parse_input(lines, lenient);
+
+    println!("{}", fs.to_json_pretty().expect("error encoding filesystem"));
+}
+
+// Lists what changed between this filesystem and the one of another transcript
+pub fn print_diff(lines: InputLines, args: &[String]) {
+    let (lenient, args) = split_lenient(args);
+    let other_filename = args.first().expect("no other input file path given");
+    let other_lines = read_normalized_lines(other_filename, &NormalizeOptions::default()).expect("error reading file");
+
+    let fs = parse_input(lines, lenient);
+    let other_fs = parse_input(other_lines, lenient);
+
+    for change in fs.diff(&other_fs) {
+        match change {
+            TreeChange::Added { path, .. } => println!("+ /{}", path.join("/")),
+            TreeChange::Removed { path, .. } => println!("- /{}", path.join("/")),
+            TreeChange::Changed { path, old, new } => println!("~ /{}: {} -> {}", path.join("/"), old.1, new.1),
+        }
+    }
+}
+
+// Lists the files and dirs matching a glob like `**/d/*.log` (relative to /)
+pub fn print_find(lines: InputLines, args: &[String]) {
+    let (lenient, args) = split_lenient(args);
+    let pattern = args.first().expect("no pattern given");
+
+    let mut fs = parse_input(lines, lenient);
+    fs.map_values(calc_sizes);
+
+    for line in find_report(&fs, pattern) {
+        println!("{}", line);
+    }
+}
+
+// one line per match, for a filesystem with computed sizes
+fn find_report(fs: &FileSystem, pattern: &str) -> Vec<String> {
+    fs.find(&Segment::parse_glob(pattern))
+      .into_iter()
+      .map(|(path, node)| match node.get_value() {
+          (FileType::Dir, size) => format!("/{} (dir, size={})", path.join("/"), size),
+          (FileType::File, size) => format!("/{} (file, size={})", path.join("/"), size),
+      })
+      .collect()
+}
+
+// Replays the transcript, telling what every command did
+pub fn print_trace(lines: InputLines, args: &[String]) {
+    let (lenient, _) = split_lenient(args);
+    parse_input_with(lines, lenient, true);
+}
+
+// Opens a shell over the parsed filesystem, running the given script, or else
+// what comes on stdin (with prompts when it's a terminal)
+pub fn run_shell(lines: InputLines, args: &[String]) {
+    let (lenient, args) = split_lenient(args);
+    let mut shell = Shell::new(parse_input(lines, lenient));
+
+    match args.first() {
+        Some(script) => {
+            let script_lines = read_normalized_lines(script, &NormalizeOptions::default()).expect("error reading script");
+            shell.run(script_lines, false);
+        },
+        None => {
+            let interactive = io::stdin().is_terminal();
+            shell.run(io::stdin().lines(), interactive);
+        },
+    }
+}
+
+// Prints every dir with its size in human readable units, largest first, with
+// optional --sort size|path, --max-depth <n> and --threshold <size> (like 10M)
+pub fn print_du(lines: InputLines, args: &[String]) {
+    let (lenient, args) = split_lenient(args);
+    let mut fs = parse_input(lines, lenient);
+    fs.map_values(calc_sizes);
+
+    print_du_report(&fs, &[], &du_options(&args));
+}
+
+#[derive(Clone, Copy)]
+enum DuSort {
+    Size,
+    Path,
+}
+
+struct DuOptions {
+    sort: DuSort,
+    max_depth: Option<usize>,
+    // smaller dirs are left out
+    threshold: i32,
+}
+
+impl Default for DuOptions {
+    fn default() -> Self {
+        DuOptions { sort: DuSort::Size, max_depth: None, threshold: 0 }
+    }
+}
+
+fn du_options(args: &[String]) -> DuOptions {
+    let mut options = DuOptions::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sort" => options.sort = match args.next().map(|s| s.as_str()) {
+                Some("size") => DuSort::Size,
+                Some("path") => DuSort::Path,
+                _ => panic!("--sort needs size or path"),
+            },
+            "--max-depth" => {
+                let depth = args.next().expect("--max-depth needs a value");
+                options.max_depth = Some(depth.parse().expect("--max-depth must be a number"));
+            },
+            "--threshold" => {
+                let threshold = args.next().expect("--threshold needs a value");
+                options.threshold = parse_size(threshold).expect("--threshold must be a size like 100, 20K or 3M");
+            },
+            other => panic!("unknown option '{}'", other),
+        }
+    }
+
+    options
+}
+
+// Prints the dirs of a filesystem with computed sizes, their paths starting
+// from base
+fn print_du_report(fs: &FileSystem, base: &[String], options: &DuOptions) {
+    for line in du_report(fs, base, options) {
+        println!("{}", line);
+    }
+}
+
+fn du_report(fs: &FileSystem, base: &[String], options: &DuOptions) -> Vec<String> {
+    let mut dirs = fs.fold_with_context(&|node, depth, path, mut dirs: Vec<(Vec<String>, i32)>| {
+        if let (FileType::Dir, size) = node.get_value() {
+            if *size >= options.threshold && options.max_depth.is_none_or(|max| depth <= max) {
+                dirs.push(([base, path].concat(), *size));
+            }
+        }
+        dirs
+    }, Vec::new());
+
+    match options.sort {
+        DuSort::Size => dirs.sort_by(|(path_a, a), (path_b, b)| b.cmp(a).then_with(|| path_a.cmp(path_b))),
+        DuSort::Path => dirs.sort(),
+    }
+
+    dirs.into_iter()
+        .map(|(path, size)| format!("{:>7}  /{}", human_size(size), path.join("/")))
+        .collect()
+}
+
+const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+
+// like du -h, in powers of 1024: 584, 92.7K, 23.8M
+fn human_size(size: i32) -> String {
+    let mut scaled = size as f64;
+    let mut unit = "";
+
+    for next in UNITS {
+        // decided on the printed value, so 1023.99K shows as 1.0M and not 1024.0K
+        if (scaled * 10.0).round() < 10240.0 {
+            break;
+        }
+        scaled /= 1024.0;
+        unit = next;
+    }
+
+    if unit.is_empty() {
+        size.to_string()
+    } else {
+        format!("{:.1}{}", scaled, unit)
+    }
+}
+
+// sizes as plain numbers or with a unit, like 20K, 1.5M or 3g
+fn parse_size(text: &str) -> Option<i32> {
+    let (number, factor) = match UNITS.iter().position(|unit| text.to_ascii_uppercase().ends_with(unit)) {
+        Some(i) => (&text[..text.len() - 1], 1024_f64.powi(i as i32 + 1)),
+        None => (text, 1.0),
+    };
+
+    let size = number.parse::<f64>().ok()? * factor;
+    if !(0.0..=i32::MAX as f64).contains(&size) {
+        return None;
+    }
+    Some(size as i32)
+}
+
+fn render_options(args: &[String]) -> RenderOptions<String, Item> {
+    let mut options = RenderOptions::new(|name, item| match item {
+        (FileType::Dir, size) => format!("{} (dir, size={})", name, size),
+        (FileType::File, size) => format!("{} (file, size={})", name, size),
+    });
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-depth" => {
+                let depth = args.next().expect("--max-depth needs a value");
+                options.max_depth = Some(depth.parse().expect("--max-depth must be a number"));
+            },
+            "--hide-leaves" => options.hide_leaves = true,
+            other => panic!("unknown option '{}'", other),
+        }
+    }
+
+    options
+}
+
+// Every subcommand takes --lenient, for transcripts that cd into dirs no ls
+// listed yet: those get created instead of failing the parse. Returns whether
+// it was given, and the other arguments.
+fn split_lenient(args: &[String]) -> (bool, Vec<String>) {
+    let rest: Vec<String> = args.iter().filter(|arg| *arg != "--lenient").cloned().collect();
+    (rest.len() < args.len(), rest)
+}
+
+fn parse_input(lines: InputLines, lenient: bool) -> FileSystem {
+    parse_input_with(lines, lenient, false)
+}
+
+// Parses the transcript, reporting the effect of every command when tracing
+fn parse_input_with(lines: InputLines, lenient: bool, trace: bool) -> FileSystem {
     // - ls adds children to the current node
     //     - dir has size 0
     //     - file has some size
     // - cd changes nodes
-    //     - cd <node> goes into a child node
+    //     - cd <node> goes into a child node, which an ls must have listed
+    //       as a dir, unless lenient and it wasn't listed at all, creating it
+    //       then
+    //     - cd <path> goes through every node along the path, where .. is
+    //       the parent node and . the node itself
     //     - cd .. goes back to the parent node
+    //     - cd / goes back to the root, wherever it happens
+    //     - cd - goes back to where the previous cd came from
 
     let commands = group_commands(lines);
 
     let root_key = "/".to_string();
     let tree: FileSystem = AddressableTree::singleton(root_key, (FileType::Dir, 0));
     let mut tree_nav = TreeNavigator::new(tree);
+    if lenient {
+        tree_nav.set_auto_create(|_| (FileType::Dir, 0));
+    }
     
     for (command, output) in commands {
+        let moves_before = tree_nav.moves().len();
+        let mut listed = 0;
+
         if command == "$ cd /" 
         {
-            // do nothing
+            tree_nav.go_to_root();
         }
-        else if command.starts_with("$ cd ..") 
+        else if command == "$ cd .." 
+        {
+            if let Err(error) = tree_nav.get_out() {
+                navigation_failed(&command, error);
+            }
+        } 
+        else if command == "$ cd -" 
         {
-            tree_nav = tree_nav.get_out()
+            if let Err(error) = tree_nav.back() {
+                navigation_failed(&command, error);
+            }
         } 
-        else if command.starts_with("$ cd") 
+        else if let Some(target) = command.strip_prefix("$ cd ") 
         {
-            let mut key = command[5..].to_string();
-            tree_nav = tree_nav.go_into(&mut key);
+            // paths like a/b, ../b or /a/b go through every dir along them
+            let path = match resolve(tree_nav.current_path(), target) {
+                Ok(path) => path,
+                Err(error) => navigation_failed(&command, error),
+            };
+            if let Some(file) = first_file(tree_nav.root(), &path) {
+                not_a_directory(&command, file);
+            }
+
+            // plain names down from here are moves into them, anything else a jump
+            let keys = &path[tree_nav.depth().min(path.len())..];
+            let downwards = !target.starts_with('/') && target.split('/').all(|key| key != "." && key != "..");
+            let moved = match keys {
+                [key] if downwards => tree_nav.go_into(key),
+                _ if downwards => tree_nav.go_into_path(keys),
+                _ => tree_nav.go_to(&path),
+            };
+            if let Err(error) = moved {
+                navigation_failed(&command, error);
+            }
         } 
         else if command.starts_with("$ ls") 
         {
+            listed = output.len();
             for output_line in output {
                 if output_line.starts_with("dir") 
                 {
-                    // case of dir
+                    // case of dir, listing it again must not wipe what is known of it
                     let key = output_line[4..].to_string();
-                    
-                    tree_nav = tree_nav.apply_to_current(move |mut t| {
-                        t.add_child(key, (FileType::Dir, 0));
-                        return t;
-                    });
+                    tree_nav.current_mut().insert_or_keep(key, (FileType::Dir, 0));
                 } 
                 else 
                 {
@@ -137,19 +423,81 @@ fn parse_input(lines: Lines<BufReader<File>>) -> FileSystem {
                     let size = size_s.parse::<i32>().unwrap();
                     let filename = filename_s.to_string();
 
-                    tree_nav = tree_nav.apply_to_current(move |mut t| {
-                        t.add_child(filename, (FileType::File, size));
-                        return t;
-                    });
+                    tree_nav.current_mut().insert_or_keep(filename, (FileType::File, size));
                 }
             }
         }
+
+        if trace {
+            let moves: Vec<String> = tree_nav.moves()[moves_before..].iter().map(describe_move).collect();
+            let effect = if command.starts_with("$ ls") {
+                format!("listed {} entries", listed)
+            } else {
+                moves.join(", ")
+            };
+            println!("{:<24} {} (now in /{})", command, effect, tree_nav.current_path().join("/"));
+        }
     }
 
     return tree_nav.get();
 }
 
-fn group_commands(mut lines: Lines<BufReader<File>>) -> Vec<(String, Vec<String>)> {
+// Path from the root that a cd target leads to, from the current one unless
+// it starts with /
+fn resolve(current: &[String], target: &str) -> Result<Vec<String>, NavigationError<String>> {
+    let mut resolved = if target.starts_with('/') { Vec::new() } else { current.to_vec() };
+
+    for key in target.split('/') {
+        match key {
+            "" | "." => {},
+            ".." => {
+                resolved.pop().ok_or(NavigationError::AtRoot)?;
+            },
+            name => resolved.push(name.to_string()),
+        }
+    }
+
+    Ok(resolved)
+}
+
+// leading part of a path that ends on a file, when it goes through one
+fn first_file<'a>(fs: &FileSystem, path: &'a [String]) -> Option<&'a [String]> {
+    (1..=path.len()).map(|depth| &path[..depth])
+                    .find(|prefix| fs.get(prefix).is_some_and(|node| node.get_value().0 == FileType::File))
+}
+
+fn describe_move(m: &Move<String>) -> String {
+    match m {
+        Move::Into(key) => format!("into {}", key),
+        Move::Out(key) => format!("out of {}", key),
+        Move::Jump { from, to } => format!("from /{} to /{}", from.join("/"), to.join("/")),
+    }
+}
+
+// A transcript moving somewhere that doesn't exist can't be made sense of
+fn navigation_failed(command: &str, error: NavigationError<String>) -> ! {
+    eprintln!("error: `{}`: {}", command, describe_error(&error));
+    std::process::exit(1);
+}
+
+// the shell reports failed moves the same way
+fn describe_error(error: &NavigationError<String>) -> String {
+    match error {
+        NavigationError::MissingChild { path, key } => format!("no directory {} in /{}", key, path.join("/")),
+        NavigationError::AtRoot => "already at /".to_string(),
+        NavigationError::NoHistory => "no previous directory".to_string(),
+        NavigationError::MissingBookmark(name) => format!("no bookmark {}", name),
+        NavigationError::NoSibling { path } => format!("no sibling to /{}", path.join("/")),
+        NavigationError::NoChildren { path } => format!("/{} is empty", path.join("/")),
+    }
+}
+
+fn not_a_directory(command: &str, path: &[String]) -> ! {
+    eprintln!("error: `{}`: /{} is not a directory", command, path.join("/"));
+    std::process::exit(1);
+}
+
+fn group_commands(mut lines: InputLines) -> Vec<(String, Vec<String>)> {
     let mut grouped_lines: Vec<(String, Vec<String>)> = Vec::new();
     
     let mut command: String;
@@ -173,4 +521,86 @@ fn group_commands(mut lines: Lines<BufReader<File>>) -> Vec<(String, Vec<String>
     grouped_lines.push((command, command_output));
 
     return grouped_lines;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufRead;
+
+    use super::*;
+
+    const TRANSCRIPT: &str = "\
+$ cd /
+$ ls
+dir a
+14848514 b.txt
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g.log
+$ cd e
+$ ls
+584 i.log
+";
+
+    fn sized(transcript: &str) -> FileSystem {
+        let mut fs = parse_input(io::Cursor::new(transcript.to_string()).lines(), false);
+        fs.map_values(calc_sizes);
+        fs
+    }
+
+    #[test]
+    fn find_lists_matches_with_their_sizes() {
+        let fs = sized(TRANSCRIPT);
+
+        assert_eq!(find_report(&fs, "**/*.log"), ["/a/g.log (file, size=2557)", "/a/e/i.log (file, size=584)"]);
+        assert_eq!(find_report(&fs, "a/*"), ["/a/e (dir, size=584)", "/a/f (file, size=29116)", "/a/g.log (file, size=2557)"]);
+        assert_eq!(find_report(&fs, ""), ["/ (dir, size=14880771)"]);
+        assert!(find_report(&fs, "zzz").is_empty());
+    }
+
+    fn du(fs: &FileSystem, args: &[&str]) -> Vec<String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        du_report(fs, &[], &du_options(&args))
+    }
+
+    #[test]
+    fn human_sizes_pick_the_unit_after_rounding() {
+        assert_eq!(human_size(0), "0");
+        assert_eq!(human_size(1023), "1023");
+        assert_eq!(human_size(1024), "1.0K");
+        assert_eq!(human_size(94925), "92.7K");
+        assert_eq!(human_size(1048524), "1023.9K");
+        assert_eq!(human_size(1048575), "1.0M");
+        assert_eq!(human_size(1048576), "1.0M");
+        assert_eq!(human_size(24933642), "23.8M");
+    }
+
+    #[test]
+    fn sizes_parse_with_any_case_units() {
+        assert_eq!(parse_size("100"), Some(100));
+        assert_eq!(parse_size("20K"), Some(20480));
+        assert_eq!(parse_size("10k"), Some(10240));
+        assert_eq!(parse_size("1.5M"), Some(1572864));
+        assert_eq!(parse_size("1g"), Some(1073741824));
+        assert_eq!(parse_size("-5"), None);
+        assert_eq!(parse_size("-1K"), None);
+        assert_eq!(parse_size("3G"), None);
+        assert_eq!(parse_size("K"), None);
+        assert_eq!(parse_size("big"), None);
+    }
+
+    #[test]
+    fn du_filters_by_depth_and_threshold() {
+        let fs = sized(TRANSCRIPT);
+
+        assert_eq!(du(&fs, &[]), ["  14.2M  /", "  31.5K  /a", "    584  /a/e"]);
+        assert_eq!(du(&fs, &["--max-depth", "1"]), ["  14.2M  /", "  31.5K  /a"]);
+        assert_eq!(du(&fs, &["--max-depth", "0"]), ["  14.2M  /"]);
+        // the threshold keeps dirs of exactly that size
+        assert_eq!(du(&fs, &["--threshold", "584"]), ["  14.2M  /", "  31.5K  /a", "    584  /a/e"]);
+        assert_eq!(du(&fs, &["--threshold", "1k"]), ["  14.2M  /", "  31.5K  /a"]);
+        assert_eq!(du(&fs, &["--threshold", "1M", "--sort", "path"]), ["  14.2M  /"]);
+    }
+}
@@ -0,0 +1,405 @@
+// Small shell over a reconstructed filesystem, taking commands one line at a
+// time, either typed in or from a script.
+
+use std::io::{self, Write};
+
+use util::{tree_navigator::TreeNavigator, tree_query::Segment, tree_view::TreeView};
+
+use crate::{calc_sizes, describe_error, print_du_report, render_options, DuOptions, DuSort, FileSystem, FileType, Item};
+
+const HELP: &str = "\
+cd [path|-]             change directory (to / without a path)
+ls [path]               list a directory
+pwd                     print the current directory
+mkdir [-p] <path>       create a directory, and its parents with -p
+touch <size> <path>     create a file, or change its size
+rm [-r] <path>          remove a file, or a directory with -r
+mv <from> <to>          move or rename a file or directory
+du [path]               size of every directory
+find [path] <glob>      files and directories matching a glob like **/*.txt
+tree [path]             draw a directory
+exit                    leave the shell";
+
+pub struct Shell {
+    nav: TreeNavigator<String, Item>,
+}
+
+impl Shell {
+    pub fn new(fs: FileSystem) -> Shell {
+        Shell { nav: TreeNavigator::new(fs) }
+    }
+
+    // Runs commands until the input ends or an exit, prompting for each one
+    // when interactive. Failed commands are reported and the shell goes on.
+    pub fn run(&mut self, mut lines: impl Iterator<Item = io::Result<String>>, interactive: bool) {
+        loop {
+            if interactive {
+                print!("{} $ ", display(self.nav.current_path()));
+                io::stdout().flush().expect("error writing prompt");
+            }
+
+            let Some(Ok(line)) = lines.next() else {
+                break;
+            };
+
+            let line = line.trim();
+            if line == "exit" || line == "quit" {
+                break;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Err(message) = self.execute(line) {
+                eprintln!("{}", message);
+            }
+        }
+    }
+
+    pub fn execute(&mut self, line: &str) -> Result<(), String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (name, args) = words.split_first().ok_or("no command")?;
+
+        let result = match (*name, args) {
+            ("cd", []) => {
+                self.nav.go_to_root();
+                Ok(())
+            },
+            ("cd", ["-"]) => self.nav.back().map_err(|error| describe_error(&error)),
+            ("cd", [path]) => self.cd(path),
+            ("ls", []) => self.ls("."),
+            ("ls", [path]) => self.ls(path),
+            ("pwd", []) => {
+                println!("{}", display(self.nav.current_path()));
+                Ok(())
+            },
+            ("mkdir", [path]) => self.mkdir(path, false),
+            ("mkdir", ["-p", path]) => self.mkdir(path, true),
+            ("touch", [size, path]) => self.touch(size, path),
+            ("rm", [path]) => self.rm(path, false),
+            ("rm", ["-r", path]) => self.rm(path, true),
+            ("mv", [from, to]) => self.mv(from, to),
+            ("du", []) => self.du("."),
+            ("du", [path]) => self.du(path),
+            ("find", [pattern]) => self.find(".", pattern),
+            ("find", [path, pattern]) => self.find(path, pattern),
+            ("tree", []) => self.tree("."),
+            ("tree", [path]) => self.tree(path),
+            ("help", []) => {
+                println!("{}", HELP);
+                Ok(())
+            },
+            ("cd" | "ls" | "pwd" | "mkdir" | "touch" | "rm" | "mv" | "du" | "find" | "tree" | "help", _) => Err("wrong arguments, see help".to_string()),
+            _ => Err("unknown command, see help".to_string()),
+        };
+
+        result.map_err(|message| format!("{}: {}", name, message))
+    }
+
+    // Absolute path of the given one, which is relative to the current
+    // directory unless it starts with /. Both . and .. are understood.
+    fn resolve(&self, path: &str) -> Vec<String> {
+        let mut resolved = if path.starts_with('/') { Vec::new() } else { self.nav.current_path().to_vec() };
+
+        for part in path.split('/') {
+            match part {
+                "" | "." => {},
+                ".." => {
+                    resolved.pop();
+                },
+                name => resolved.push(name.to_string()),
+            }
+        }
+
+        resolved
+    }
+
+    // absolute path and node of an existing file or directory
+    fn lookup(&self, path: &str) -> Result<(Vec<String>, &FileSystem), String> {
+        let resolved = self.resolve(path);
+        match self.nav.root().get(&resolved) {
+            Some(node) => Ok((resolved, node)),
+            None => Err(format!("{}: no such file or directory", path)),
+        }
+    }
+
+    // Path of a new entry, split into the parent dir (which must exist) and
+    // the name.
+    fn new_entry(&self, path: &str) -> Result<(Vec<String>, String), String> {
+        let mut parent = self.resolve(path);
+        let name = parent.pop().ok_or(format!("{}: already exists", path))?;
+
+        match self.nav.root().get(&parent) {
+            Some(node) if is_dir(node) => Ok((parent, name)),
+            Some(_) => Err(format!("{}: not a directory", display(&parent))),
+            None => Err(format!("{}: no such directory", display(&parent))),
+        }
+    }
+
+    fn cd(&mut self, path: &str) -> Result<(), String> {
+        let (resolved, node) = self.lookup(path)?;
+        if !is_dir(node) {
+            return Err(format!("{}: not a directory", path));
+        }
+
+        self.nav.go_to(&resolved).map_err(|error| describe_error(&error))
+    }
+
+    fn ls(&self, path: &str) -> Result<(), String> {
+        let (_, node) = self.lookup(path)?;
+
+        // a file lists itself
+        let entries = if is_dir(node) { node.get_children() } else { vec![node] };
+        for entry in entries {
+            match entry.get_value() {
+                (FileType::Dir, _) => println!("dir {}", entry.get_key()),
+                (FileType::File, size) => println!("{} {}", size, entry.get_key()),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn mkdir(&mut self, path: &str, parents: bool) -> Result<(), String> {
+        let resolved = self.resolve(path);
+        if resolved.is_empty() && !parents {
+            return Err("/: already exists".to_string());
+        }
+
+        // every existing node along the way must be a dir
+        for depth in 1..=resolved.len() {
+            match self.nav.root().get(&resolved[..depth]) {
+                Some(node) if !is_dir(node) => return Err(format!("{}: not a directory", display(&resolved[..depth]))),
+                Some(_) if depth == resolved.len() && !parents => return Err(format!("{}: already exists", path)),
+                Some(_) => {},
+                None if depth < resolved.len() && !parents => return Err(format!("{}: no such directory", display(&resolved[..depth]))),
+                None => break,
+            }
+        }
+
+        if let Some((name, parent)) = resolved.split_last() {
+            self.nav.edit_root(|root| {
+                if root.get(&resolved).is_none() {
+                    root.insert_at_creating(parent, name.clone(), (FileType::Dir, 0), |_| (FileType::Dir, 0));
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    fn touch(&mut self, size: &str, path: &str) -> Result<(), String> {
+        let size: i32 = size.parse().map_err(|_| format!("{}: not a size", size))?;
+        let (parent, name) = self.new_entry(path)?;
+
+        let target = [parent.as_slice(), std::slice::from_ref(&name)].concat();
+        if self.nav.root().get(&target).is_some_and(is_dir) {
+            return Err(format!("{}: is a directory", path));
+        }
+
+        self.nav.edit_root(|root| {
+            root.get_mut(&parent)
+                .expect("parent was just looked up")
+                .replace(name, (FileType::File, size));
+        });
+        Ok(())
+    }
+
+    fn rm(&mut self, path: &str, recursive: bool) -> Result<(), String> {
+        let (resolved, node) = self.lookup(path)?;
+
+        if resolved.is_empty() {
+            return Err("cannot remove /".to_string());
+        }
+        if is_dir(node) && !recursive {
+            return Err(format!("{}: is a directory, use -r", path));
+        }
+
+        self.nav.edit_root(|root| root.remove_at(&resolved));
+        Ok(())
+    }
+
+    fn mv(&mut self, from: &str, to: &str) -> Result<(), String> {
+        let (source, node) = self.lookup(from)?;
+        let source_is_dir = is_dir(node);
+
+        if source.is_empty() {
+            return Err("cannot move /".to_string());
+        }
+
+        // moving into an existing dir keeps the name
+        let mut target = self.resolve(to);
+        if let Some(existing) = self.nav.root().get(&target) {
+            if is_dir(existing) {
+                target.push(source[source.len() - 1].clone());
+            }
+        }
+
+        if target.starts_with(&source) {
+            return Err(format!("cannot move {} into itself", from));
+        }
+
+        match self.nav.root().get(&target) {
+            Some(existing) if is_dir(existing) => return Err(format!("{}: already exists", display(&target))),
+            Some(_) if source_is_dir => return Err(format!("{}: cannot overwrite a file with a directory", display(&target))),
+            _ => {},
+        }
+
+        let (parent, name) = self.new_entry(&display(&target))?;
+        self.nav.edit_root(|root| {
+            let node = root.remove_at(&source).expect("source was just looked up");
+            root.get_mut(&parent)
+                .expect("target parent was just looked up")
+                .add_child_node(name, node);
+        });
+
+        Ok(())
+    }
+
+    fn du(&self, path: &str) -> Result<(), String> {
+        let (resolved, _) = self.lookup(path)?;
+        let fs = self.sized();
+        let node = fs.get(&resolved).expect("path was just looked up");
+
+        print_du_report(node, &resolved, &DuOptions { sort: DuSort::Path, ..DuOptions::default() });
+        Ok(())
+    }
+
+    fn find(&self, path: &str, pattern: &str) -> Result<(), String> {
+        let (resolved, node) = self.lookup(path)?;
+
+        for (path, _) in node.find(&Segment::parse_glob(pattern)) {
+            println!("{}", display(&[resolved.as_slice(), &path].concat()));
+        }
+
+        Ok(())
+    }
+
+    fn tree(&self, path: &str) -> Result<(), String> {
+        let (resolved, _) = self.lookup(path)?;
+        let fs = self.sized();
+        let node = fs.get(&resolved).expect("path was just looked up");
+
+        print!("{}", node.render(&render_options(&[])));
+        Ok(())
+    }
+
+    // copy of the filesystem with dir sizes computed
+    fn sized(&self) -> FileSystem {
+        let mut fs = self.nav.root().clone();
+        fs.map_values(calc_sizes);
+        fs
+    }
+}
+
+fn is_dir(node: &FileSystem) -> bool {
+    matches!(node.get_value(), (FileType::Dir, _))
+}
+
+fn display(path: &[String]) -> String {
+    format!("/{}", path.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, BufRead};
+
+    use crate::parse_input;
+
+    use super::*;
+
+    // /a/c.txt, /b.txt
+    fn shell() -> Shell {
+        let transcript = "$ cd /\n$ ls\ndir a\n10 b.txt\n$ cd a\n$ ls\n20 c.txt\n";
+        Shell::new(parse_input(io::Cursor::new(transcript.to_string()).lines(), false))
+    }
+
+    fn node<'a>(shell: &'a Shell, path: &str) -> Option<&'a FileSystem> {
+        shell.nav.root().get(&shell.resolve(path))
+    }
+
+    #[test]
+    fn mkdir_creates_parents_only_with_p() {
+        let mut shell = shell();
+
+        assert_eq!(shell.execute("mkdir x/y"), Err("mkdir: /x: no such directory".to_string()));
+        assert!(node(&shell, "/x").is_none());
+
+        shell.execute("mkdir -p x/y").unwrap();
+        assert!(node(&shell, "/x").is_some_and(is_dir));
+        assert!(node(&shell, "/x/y").is_some_and(is_dir));
+
+        assert_eq!(shell.execute("mkdir x"), Err("mkdir: x: already exists".to_string()));
+        assert_eq!(shell.execute("mkdir -p x/y"), Ok(()));
+        assert_eq!(shell.execute("mkdir -p b.txt/z"), Err("mkdir: /b.txt: not a directory".to_string()));
+    }
+
+    #[test]
+    fn touch_creates_or_resizes_files() {
+        let mut shell = shell();
+
+        shell.execute("touch 5 a/d.txt").unwrap();
+        assert_eq!(node(&shell, "/a/d.txt").map(|node| node.get_value().clone()), Some((FileType::File, 5)));
+
+        shell.execute("touch 7 b.txt").unwrap();
+        assert_eq!(node(&shell, "/b.txt").map(|node| node.get_value().clone()), Some((FileType::File, 7)));
+
+        assert_eq!(shell.execute("touch 1 a"), Err("touch: a: is a directory".to_string()));
+        assert_eq!(shell.execute("touch big b.txt"), Err("touch: big: not a size".to_string()));
+    }
+
+    #[test]
+    fn rm_needs_r_for_directories() {
+        let mut shell = shell();
+
+        shell.execute("rm b.txt").unwrap();
+        assert!(node(&shell, "/b.txt").is_none());
+
+        assert_eq!(shell.execute("rm a"), Err("rm: a: is a directory, use -r".to_string()));
+        assert!(node(&shell, "/a/c.txt").is_some());
+
+        shell.execute("rm -r a").unwrap();
+        assert!(node(&shell, "/a").is_none());
+        assert_eq!(shell.execute("rm -r /"), Err("rm: cannot remove /".to_string()));
+    }
+
+    #[test]
+    fn mv_into_a_directory_keeps_the_name() {
+        let mut shell = shell();
+
+        shell.execute("mv b.txt a").unwrap();
+        assert!(node(&shell, "/b.txt").is_none());
+        assert_eq!(node(&shell, "/a/b.txt").map(|node| node.get_value().clone()), Some((FileType::File, 10)));
+
+        shell.execute("mv a/c.txt /d.txt").unwrap();
+        assert!(node(&shell, "/d.txt").is_some());
+        assert!(node(&shell, "/a/c.txt").is_none());
+    }
+
+    #[test]
+    fn mv_cannot_move_into_itself() {
+        let mut shell = shell();
+        shell.execute("mkdir a/sub").unwrap();
+
+        assert_eq!(shell.execute("mv a a/sub"), Err("mv: cannot move a into itself".to_string()));
+        assert_eq!(shell.execute("mv a a"), Err("mv: cannot move a into itself".to_string()));
+        assert!(node(&shell, "/a/sub").is_some());
+    }
+
+    #[test]
+    fn cd_only_enters_directories() {
+        let mut shell = shell();
+
+        assert_eq!(shell.execute("cd -"), Err("cd: no previous directory".to_string()));
+
+        shell.execute("cd a").unwrap();
+        assert_eq!(shell.nav.current_path(), &["a"]);
+
+        assert_eq!(shell.execute("cd c.txt"), Err("cd: c.txt: not a directory".to_string()));
+        assert_eq!(shell.execute("cd /nowhere"), Err("cd: /nowhere: no such file or directory".to_string()));
+        assert_eq!(shell.nav.current_path(), &["a"]);
+
+        shell.execute("cd -").unwrap();
+        assert!(shell.nav.current_path().is_empty());
+    }
+}
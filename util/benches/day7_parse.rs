@@ -0,0 +1,118 @@
+// Compares parsing a day7 transcript into a TreeNavigator-built AddressableTree
+// against an ArenaTree, on generated transcripts of increasing size.
+//
+// Use: cargo bench --bench day7_parse
+
+use std::{hint::black_box, time::{Duration, Instant}};
+
+use util::{addressable_tree::AddressableTree, arena_tree::ArenaTree, tree_navigator::TreeNavigator};
+
+type Item = (bool, i32);
+
+fn main() {
+    for dirs in [1_000, 10_000, 100_000] {
+        let transcript = generate_transcript(dirs);
+
+        let navigator = time(|| parse_with_navigator(&transcript).get_children().len());
+        let arena = time(|| parse_with_arena(&transcript).len());
+        let arena_converted = time(|| AddressableTree::from(parse_with_arena(&transcript)).get_children().len());
+
+        println!("{} dirs, {} lines", dirs, transcript.len());
+        println!("    navigator:              {:?}", navigator);
+        println!("    arena:                  {:?}", arena);
+        println!("    arena + conversion:     {:?}", arena_converted);
+    }
+}
+
+// best of a few runs
+fn time<T>(f: impl Fn() -> T) -> Duration {
+    (0..5).map(|_| {
+              let start = Instant::now();
+              black_box(f());
+              start.elapsed()
+          })
+          .min()
+          .unwrap()
+}
+
+// Generates a transcript exploring a pseudo-random filesystem depth-first,
+// listing every dir (with up to 9 subdirs and 9 files) before going into its
+// subdirs, like the day7 inputs do.
+fn generate_transcript(dirs: usize) -> Vec<String> {
+    let mut lines = vec!["$ cd /".to_string()];
+    let mut seed: u64 = 2022;
+    let mut budget = dirs;
+
+    explore(&mut lines, &mut seed, &mut budget, 0);
+
+    lines
+}
+
+fn explore(lines: &mut Vec<String>, seed: &mut u64, budget: &mut usize, depth: usize) {
+    *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+
+    let subdirs = if depth < 12 { ((*seed >> 33) % 10) as usize } else { 0 };
+    let subdirs = subdirs.min(*budget);
+    *budget -= subdirs;
+
+    lines.push("$ ls".to_string());
+    for d in 0..subdirs {
+        lines.push(format!("dir d{}", d));
+    }
+    for f in 0..(*seed >> 40) % 10 {
+        lines.push(format!("{} f{}.txt", (*seed >> f) % 100000, f));
+    }
+
+    for d in 0..subdirs {
+        lines.push(format!("$ cd d{}", d));
+        explore(lines, seed, budget, depth + 1);
+        lines.push("$ cd ..".to_string());
+    }
+}
+
+fn parse_with_navigator(lines: &[String]) -> AddressableTree<String, Item> {
+    let tree = AddressableTree::singleton("/".to_string(), (true, 0));
+    let mut tree_nav = TreeNavigator::new(tree);
+
+    for line in &lines[1..] {
+        if line == "$ cd .." {
+            tree_nav.get_out().unwrap();
+        } else if let Some(dir) = line.strip_prefix("$ cd ") {
+            tree_nav.go_into(&dir.to_string()).unwrap();
+        } else if line == "$ ls" {
+            // nothing to do, output follows
+        } else if let Some(dir) = line.strip_prefix("dir ") {
+            let key = dir.to_string();
+            tree_nav.current_mut().add_child(key, (true, 0));
+        } else {
+            let (size, filename) = line.split_once(' ').unwrap();
+            let size = size.parse().unwrap();
+            let key = filename.to_string();
+            tree_nav.current_mut().add_child(key, (false, size));
+        }
+    }
+
+    tree_nav.get()
+}
+
+fn parse_with_arena(lines: &[String]) -> ArenaTree<String, Item> {
+    let mut tree = ArenaTree::new("/".to_string(), (true, 0));
+    let mut current = tree.root();
+
+    for line in &lines[1..] {
+        if line == "$ cd .." {
+            current = tree.parent(current).unwrap();
+        } else if let Some(dir) = line.strip_prefix("$ cd ") {
+            current = tree.child(current, &dir.to_string()).unwrap();
+        } else if line == "$ ls" {
+            // nothing to do, output follows
+        } else if let Some(dir) = line.strip_prefix("dir ") {
+            tree.add_child(current, dir.to_string(), (true, 0));
+        } else {
+            let (size, filename) = line.split_once(' ').unwrap();
+            tree.add_child(current, filename.to_string(), (false, size.parse().unwrap()));
+        }
+    }
+
+    tree
+}
@@ -0,0 +1,70 @@
+// Compares fold and map_values against their parallel counterparts on
+// generated trees of increasing size. Gains depend on the cores available, on
+// a single one the parallel versions only add overhead.
+//
+// Use: cargo bench --bench tree_parallel
+
+use std::{hint::black_box, time::{Duration, Instant}};
+
+use util::{addressable_tree::AddressableTree, tree_parallel::Parallelism, tree_view::TreeView};
+
+type Tree = AddressableTree<u32, u64>;
+
+fn main() {
+    let parallelism = Parallelism::default();
+
+    for levels in [4, 5, 6] {
+        let tree = generate_tree(levels);
+
+        let fold = time(|| tree.fold(&|node, acc| acc + work(node), 0));
+        let par_fold = time(|| tree.par_fold(&work, &|a, b| a + b, parallelism));
+        assert_eq!(tree.fold(&|node, acc| acc + work(node), 0), tree.par_fold(&work, &|a, b| a + b, parallelism));
+
+        let map_values = time(|| tree.clone().map_values(subtree_sum));
+        let par_map_values = time(|| tree.clone().par_map_values(&subtree_sum, parallelism));
+
+        println!("{} nodes", tree.len() + 1);
+        println!("    fold:                   {:?}", fold);
+        println!("    par_fold:               {:?}", par_fold);
+        println!("    map_values:             {:?}", map_values);
+        println!("    par_map_values:         {:?}", par_map_values);
+    }
+}
+
+// best of a few runs
+fn time<T>(f: impl Fn() -> T) -> Duration {
+    (0..5).map(|_| {
+              let start = Instant::now();
+              black_box(f());
+              start.elapsed()
+          })
+          .min()
+          .unwrap()
+}
+
+// Full tree with 10 children per node, over the given number of levels below
+// the root.
+fn generate_tree(levels: u32) -> Tree {
+    let mut tree = AddressableTree::singleton(0, 0);
+
+    if levels > 0 {
+        for key in 0..10 {
+            let mut child = generate_tree(levels - 1);
+            child.map_values(|node| *node.get_value() + key as u64);
+            tree.add_child_node(key, child);
+        }
+    }
+
+    tree
+}
+
+// some made up work per node, so there's something to parallelize
+fn work(node: &Tree) -> u64 {
+    (0..64).fold(*node.get_value(), |acc, i| acc.wrapping_mul(31).wrapping_add(i))
+}
+
+fn subtree_sum(node: &Tree) -> u64 {
+    node.get_children()
+        .iter()
+        .fold(work(node), |acc, child| acc.wrapping_add(*child.get_value()))
+}
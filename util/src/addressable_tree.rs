@@ -1,17 +1,75 @@
-use std::collections::HashMap;
+use std::{cmp::Ordering, collections::{hash_map::DefaultHasher, HashMap}, fmt, hash::{Hash, Hasher}};
 
-// non-empty tree for simplicity
+use crate::tree_view::{child_path, TreeView};
+
+// How children are ordered, which in turn orders get_children, folds and iterators.
+pub enum ChildOrder<K> {
+    // order in which children were first added
+    Insertion,
+    // sorted with the given comparator on keys
+    ByKey(fn(&K, &K) -> Ordering),
+}
+
+impl <K: Ord> ChildOrder<K> {
+    pub fn sorted() -> ChildOrder<K> {
+        ChildOrder::ByKey(K::cmp)
+    }
+}
+
+impl <K> Clone for ChildOrder<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl <K> Copy for ChildOrder<K> {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TreeError<K> {
+    // a child with this key already exists
+    DuplicateKey(K),
+    // there is no child with this key
+    MissingKey(K),
+}
+
+impl <K: fmt::Debug> fmt::Display for TreeError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeError::DuplicateKey(key) => write!(f, "child {:?} already exists", key),
+            TreeError::MissingKey(key) => write!(f, "no child {:?}", key),
+        }
+    }
+}
+
+impl <K: fmt::Debug> std::error::Error for TreeError<K> {}
+
+// non-empty tree for simplicity: there is always a root node, which acts as
+// the anchor of the tree (like `/` in a filesystem) rather than as content.
+// An empty tree is one whose root has no children, and the size statistics
+// (len, depth, leaf_count) only count what is below the root.
+#[derive(Clone)]
 pub struct AddressableTree<K, V> {
     key: K,
     value: V,
-    children: HashMap<K, AddressableTree<K, V>>,
+    // children in order, with their positions indexed by key
+    children: Vec<AddressableTree<K, V>>,
+    index: HashMap<K, usize>,
+    order: ChildOrder<K>,
 }
 
 impl <K: Eq + core::hash::Hash + PartialEq + Clone, V> AddressableTree<K, V> {
     pub fn singleton(key: K, value: V) -> AddressableTree<K, V> {
         AddressableTree { key: key
                         , value: value
-                        , children: HashMap::new() }
+                        , children: Vec::new()
+                        , index: HashMap::new()
+                        , order: ChildOrder::Insertion }
+    }
+
+    // root-only tree
+    pub fn empty() -> AddressableTree<K, V>
+    where K: Default, V: Default {
+        AddressableTree::singleton(K::default(), V::default())
     }
 
     pub fn get_key(&self) -> &K {
@@ -25,47 +83,760 @@ impl <K: Eq + core::hash::Hash + PartialEq + Clone, V> AddressableTree<K, V> {
     pub fn get_children(&self) -> Vec<&AddressableTree<K, V>> {
         let mut ret = Vec::new();
 
-        for (_, value) in &self.children {
+        for value in &self.children {
             ret.push(value);
         }
 
         return ret;
     }
 
+    pub fn get_order(&self) -> ChildOrder<K> {
+        self.order
+    }
+
+    // Sets the ordering policy of this node and all its descendants, reordering
+    // their current children. Children added later inherit it from their parent.
+    pub fn set_order(&mut self, order: ChildOrder<K>) {
+        self.order = order;
+
+        if let ChildOrder::ByKey(cmp) = order {
+            self.children.sort_by(|a, b| cmp(&a.key, &b.key));
+            self.reindex(0);
+        }
+
+        for child in self.children.iter_mut() {
+            child.set_order(order);
+        }
+    }
+
+    // Adding a child whose key is already taken replaces the existing child,
+    // along with its whole subtree. See try_add_child, insert_or_keep and
+    // replace for explicit behaviours.
+
     pub fn add_child(&mut self, key: K, value: V) {
-        let key_clone = key.clone();
-        self.children.insert(key, AddressableTree::singleton(key_clone, value));
+        self.replace(key, value);
     }
 
     pub fn add_child_node(&mut self, key: K, node:AddressableTree<K, V>) {
-        self.children.insert(key, node);
+        self.replace_node(key, node);
     }
 
-    pub fn remove_child(&mut self, key: &mut K) -> AddressableTree<K, V> {
-        self.children.remove(key).unwrap()
+    // panics if there is no such child, see try_remove_child
+    pub fn remove_child(&mut self, key: &K) -> AddressableTree<K, V> {
+        self.detach_child(key).unwrap()
     }
 
-    pub fn map_values(&mut self, f: fn(&AddressableTree<K, V>) -> V)
-    {
+    // adds a child, failing if the key is already taken
+    pub fn try_add_child(&mut self, key: K, value: V) -> Result<(), TreeError<K>> {
+        let child = self.new_child(key.clone(), value);
+        self.try_add_child_node(key, child)
+    }
+
+    pub fn try_add_child_node(&mut self, key: K, node: AddressableTree<K, V>) -> Result<(), TreeError<K>> {
+        if self.index.contains_key(&key) {
+            return Err(TreeError::DuplicateKey(key));
+        }
+
+        self.insert_child(key, node);
+        Ok(())
+    }
+
+    // Adds a child unless the key is already taken, in which case the existing
+    // child (and its subtree) is kept. Returns whether the child was added.
+    pub fn insert_or_keep(&mut self, key: K, value: V) -> bool {
+        if self.index.contains_key(&key) {
+            return false;
+        }
+
+        self.add_child(key, value);
+        true
+    }
+
+    // Adds a child, returning the one it replaced (with its subtree), if any.
+    pub fn replace(&mut self, key: K, value: V) -> Option<AddressableTree<K, V>> {
+        let child = self.new_child(key.clone(), value);
+        self.replace_node(key, child)
+    }
+
+    pub fn replace_node(&mut self, key: K, node: AddressableTree<K, V>) -> Option<AddressableTree<K, V>> {
+        self.insert_child(key, node)
+    }
+
+    pub fn try_remove_child(&mut self, key: &K) -> Result<AddressableTree<K, V>, TreeError<K>> {
+        match self.detach_child(key) {
+            Some(node) => Ok(node),
+            None => Err(TreeError::MissingKey(key.clone())),
+        }
+    }
+
+    // new childless node, inheriting this node's ordering policy
+    fn new_child(&self, key: K, value: V) -> AddressableTree<K, V> {
+        let mut child = AddressableTree::singleton(key, value);
+        child.order = self.order;
+        child
+    }
+
+    fn detach_child(&mut self, key: &K) -> Option<AddressableTree<K, V>> {
+        let i = self.index.remove(key)?;
+        let node = self.children.remove(i);
+        self.reindex(i);
+        Some(node)
+    }
+
+    // Breaks the node apart into its key, value and children (in order).
+    pub(crate) fn into_parts(self) -> (K, V, Vec<AddressableTree<K, V>>) {
+        (self.key, self.value, self.children)
+    }
+
+    // Puts a node back together, children ending up ordered by the given policy.
+    pub(crate) fn from_parts( key: K
+                            , value: V
+                            , order: ChildOrder<K>
+                            , children: Vec<AddressableTree<K, V>>
+                            ) -> AddressableTree<K, V> {
+        let mut tree = AddressableTree::singleton(key, value);
+        tree.order = order;
+
+        for child in children {
+            tree.insert_child(child.key.clone(), child);
+        }
+
+        tree
+    }
+
+    // Children can be changed in place, but not rekeyed nor moved around.
+    pub(crate) fn children_mut(&mut self) -> &mut [AddressableTree<K, V>] {
+        &mut self.children
+    }
+
+    pub(crate) fn set_value(&mut self, value: V) {
+        self.value = value;
+    }
+
+    // position of a child among its siblings, in child order
+    pub(crate) fn position(&self, key: &K) -> Option<usize> {
+        self.index.get(key).copied()
+    }
+
+    pub(crate) fn child_at(&self, position: usize) -> Option<&AddressableTree<K, V>> {
+        self.children.get(position)
+    }
+
+    fn child_mut(&mut self, key: &K) -> Option<&mut AddressableTree<K, V>> {
+        self.index.get(key).map(|&i| &mut self.children[i])
+    }
+
+    // Inserts a child where the ordering policy puts it. An existing child
+    // with the same key is replaced in place, and returned.
+    fn insert_child(&mut self, key: K, mut node: AddressableTree<K, V>) -> Option<AddressableTree<K, V>> {
+        node.key = key;
+
+        if let Some(&i) = self.index.get(&node.key) {
+            return Some(std::mem::replace(&mut self.children[i], node));
+        }
+
+        let i = match self.order {
+            ChildOrder::Insertion => self.children.len(),
+            ChildOrder::ByKey(cmp) => self.children.partition_point(|c| cmp(&c.key, &node.key) == Ordering::Less),
+        };
+
+        self.children.insert(i, node);
+        self.reindex(i);
+        None
+    }
+
+    // refreshes the positions of children from the given one onwards
+    fn reindex(&mut self, from: usize) {
+        for (i, child) in self.children.iter().enumerate().skip(from) {
+            self.index.insert(child.key.clone(), i);
+        }
+    }
+
+    // Paths lead from this node down, like for TreeView::get.
+
+    pub fn get_mut(&mut self, path: &[K]) -> Option<&mut AddressableTree<K, V>> {
+        let mut node = self;
+        for key in path {
+            node = node.child_mut(key)?;
+        }
+        Some(node)
+    }
+
+    // Inserts a child under the node at path, returning it, or None when there
+    // is no node at path.
+    // Like add_child, this replaces an existing child with the same key along
+    // with its whole subtree.
+    pub fn insert_at(&mut self, path: &[K], key: K, value: V) -> Option<&mut AddressableTree<K, V>> {
+        let node = self.get_mut(path)?;
+        node.add_child(key.clone(), value);
+        node.child_mut(&key)
+    }
+
+    // Same as insert_at, creating the missing nodes along the path with values
+    // from make_parent.
+    pub fn insert_at_creating( &mut self
+                             , path: &[K]
+                             , key: K
+                             , value: V
+                             , mut make_parent: impl FnMut(&K) -> V
+                             ) -> &mut AddressableTree<K, V> {
+        let mut node = self;
+        for parent_key in path {
+            if node.child(parent_key).is_none() {
+                node.add_child(parent_key.clone(), make_parent(parent_key));
+            }
+            node = node.child_mut(parent_key).expect("child was just added");
+        }
+
+        node.add_child(key.clone(), value);
+        node.child_mut(&key).expect("child was just added")
+    }
+
+    // Removes and returns the node at path (and its subtree).
+    // The node this is called on can't remove itself, so the empty path gives None.
+    pub fn remove_at(&mut self, path: &[K]) -> Option<AddressableTree<K, V>> {
+        let (key, parent_path) = path.split_last()?;
+        self.get_mut(parent_path)?.detach_child(key)
+    }
+
+    pub fn map_values<F>(&mut self, mut f: F)
+    where F: FnMut(&AddressableTree<K, V>) -> V {
+        self.map_values_by(&mut f);
+    }
+
+    fn map_values_by<F>(&mut self, f: &mut F)
+    where F: FnMut(&AddressableTree<K, V>) -> V {
         // map children
-        for child in self.children.values_mut() {
-            child.map_values(f);
+        for child in self.children.iter_mut() {
+            child.map_values_by(f);
         }
 
         // map value
-        self.value = f(&self);
+        self.value = f(self);
+    }
+
+    // Maps every value independently (parents before children) into a new tree
+    // of the same shape.
+    pub fn map<W, F>(self, mut f: F) -> AddressableTree<K, W>
+    where F: FnMut(&K, V) -> W {
+        self.map_by(&mut f)
+    }
+
+    fn map_by<W, F>(self, f: &mut F) -> AddressableTree<K, W>
+    where F: FnMut(&K, V) -> W {
+        let value = f(&self.key, self.value);
+        let children = self.children.into_iter()
+                                    .map(|child| child.map_by(f))
+                                    .collect();
+
+        AddressableTree { key: self.key
+                        , value
+                        , children
+                        , index: self.index
+                        , order: self.order }
+    }
+
+    // Maps values into a new tree of the same shape, children first, so each
+    // value is mapped knowing the already mapped children of its node.
+    pub fn map_bottom_up<W, F>(self, mut f: F) -> AddressableTree<K, W>
+    where F: FnMut(&K, V, &[AddressableTree<K, W>]) -> W {
+        self.map_bottom_up_by(&mut f)
+    }
+
+    fn map_bottom_up_by<W, F>(self, f: &mut F) -> AddressableTree<K, W>
+    where F: FnMut(&K, V, &[AddressableTree<K, W>]) -> W {
+        let children: Vec<_> = self.children.into_iter()
+                                            .map(|child| child.map_bottom_up_by(f))
+                                            .collect();
+        let value = f(&self.key, self.value, &children);
+
+        AddressableTree { key: self.key
+                        , value
+                        , children
+                        , index: self.index
+                        , order: self.order }
+    }
+
+    // values can be mutated, the shape of the tree can't
+    pub fn pre_order_mut(&mut self) -> PreOrderMut<'_, K, V> {
+        PreOrderMut { stack: vec![(Vec::new(), self)] }
+    }
+}
+
+// Queries, with children looked up through the index.
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V> TreeView for AddressableTree<K, V> {
+    type Key = K;
+    type Value = V;
+
+    fn key(&self) -> &K {
+        &self.key
+    }
+
+    fn value(&self) -> &V {
+        &self.value
+    }
+
+    fn children(&self) -> &[AddressableTree<K, V>] {
+        &self.children
+    }
+
+    fn child(&self, key: &K) -> Option<&AddressableTree<K, V>> {
+        self.index.get(key).map(|&i| &self.children[i])
+    }
+}
+
+impl <K: fmt::Debug, V: fmt::Debug> fmt::Debug for AddressableTree<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AddressableTree")
+         .field("key", &self.key)
+         .field("value", &self.value)
+         .field("children", &self.children)
+         .finish()
+    }
+}
+
+// Children are addressed by key, so trees are equal when they hold the same
+// keys and values at the same paths, whatever the order of their children.
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V: PartialEq> PartialEq for AddressableTree<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+            && self.value == other.value
+            && self.children.len() == other.children.len()
+            && self.children.iter().all(|child| other.child(&child.key) == Some(child))
+    }
+}
+
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V: Eq> Eq for AddressableTree<K, V> {}
+
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V: Hash> Hash for AddressableTree<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+        self.value.hash(state);
+
+        // combined regardless of order, to agree with PartialEq
+        let children = self.children.iter().fold(0u64, |acc, child| {
+            let mut hasher = DefaultHasher::new();
+            child.hash(&mut hasher);
+            acc.wrapping_add(hasher.finish())
+        });
+        self.children.len().hash(state);
+        children.hash(state);
+    }
+}
+
+// Builds a tree out of (path, value) pairs, under a default root. Nodes along
+// the paths that are not given a value get a default one.
+impl <K, V> FromIterator<(Vec<K>, V)> for AddressableTree<K, V>
+where K: Eq + core::hash::Hash + PartialEq + Clone + Default, V: Default {
+    fn from_iter<I: IntoIterator<Item = (Vec<K>, V)>>(iter: I) -> Self {
+        let mut tree = AddressableTree::empty();
+        tree.extend(iter);
+        tree
     }
+}
+
+// Sets the value at each path (the empty path being the root), creating
+// missing nodes along the way with default values.
+impl <K, V> Extend<(Vec<K>, V)> for AddressableTree<K, V>
+where K: Eq + core::hash::Hash + PartialEq + Clone, V: Default {
+    fn extend<I: IntoIterator<Item = (Vec<K>, V)>>(&mut self, iter: I) {
+        for (path, value) in iter {
+            let mut node = &mut *self;
+
+            for key in path {
+                if node.child(&key).is_none() {
+                    node.add_child(key.clone(), V::default());
+                }
+                node = node.child_mut(&key).expect("child was just added");
+            }
+
+            node.value = value;
+        }
+    }
+}
+
+pub struct PreOrderMut<'a, K, V> {
+    stack: Vec<(Vec<K>, &'a mut AddressableTree<K, V>)>,
+}
+
+impl <'a, K: Clone, V> Iterator for PreOrderMut<'a, K, V> {
+    type Item = (Vec<K>, &'a K, &'a mut V);
 
-    pub fn fold<T>(&self, f: &impl Fn(&AddressableTree<K, V>, T) -> T, initial: T) -> T
-    {
-        let mut acc = initial;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, node) = self.stack.pop()?;
+        let AddressableTree { key, value, children, .. } = node;
 
-        for child in self.children.values() {
-            acc = child.fold(f, acc);
+        for child in children.iter_mut().rev() {
+            self.stack.push((child_path(&path, &child.key), child));
         }
 
-        acc = f(self, acc);
+        Some((path, &*key, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, ops::ControlFlow};
+
+    use super::*;
+
+    fn path(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    // same tree, children added in a different order
+    fn pair() -> (AddressableTree<String, i32>, AddressableTree<String, i32>) {
+        let a: AddressableTree<String, i32> = [ (path(&["x"]), 1), (path(&["y"]), 2), (path(&["y", "z"]), 3) ].into_iter().collect();
+        let b: AddressableTree<String, i32> = [ (path(&["y", "z"]), 3), (path(&["y"]), 2), (path(&["x"]), 1) ].into_iter().collect();
+        (a, b)
+    }
+
+    // a(1) holding b(2) and c(3), which holds d(4), then e(5)
+    fn sample() -> AddressableTree<String, i32> {
+        [ (path(&["a"]), 1), (path(&["a", "b"]), 2), (path(&["a", "c"]), 3), (path(&["a", "c", "d"]), 4), (path(&["e"]), 5) ].into_iter().collect()
+    }
+
+    fn paths<'a>(nodes: impl Iterator<Item = (Vec<String>, &'a AddressableTree<String, i32>)>) -> Vec<String> {
+        nodes.map(|(path, node)| {
+                 assert_eq!(path.last().unwrap_or(node.get_key()), node.get_key());
+                 path.join("/")
+             })
+             .collect()
+    }
+
+    fn hash_of(tree: &AddressableTree<String, i32>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        tree.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn from_iter_builds_paths_with_default_parents() {
+        let tree: AddressableTree<String, i32> = [ (path(&["a", "b"]), 2), (Vec::new(), 7) ].into_iter().collect();
+
+        assert_eq!(tree.get_value(), &7);
+        assert_eq!(tree.get(&path(&["a"])).map(|node| *node.get_value()), Some(0));
+        assert_eq!(tree.get(&path(&["a", "b"])).map(|node| *node.get_value()), Some(2));
+    }
+
+    #[test]
+    fn extend_overwrites_values_but_keeps_subtrees() {
+        let (mut tree, _) = pair();
+        tree.extend([ (path(&["y"]), 20) ]);
 
-        return acc;
+        assert_eq!(tree.get(&path(&["y"])).map(|node| *node.get_value()), Some(20));
+        assert!(tree.get(&path(&["y", "z"])).is_some());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn equality_ignores_child_order() {
+        let (a, b) = pair();
+
+        let order = |tree: &AddressableTree<String, i32>| tree.get_children().iter().map(|child| child.get_key().clone()).collect::<Vec<_>>();
+        assert_ne!(order(&a), order(&b));
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn equality_sees_values_keys_and_shape() {
+        let (a, _) = pair();
+
+        let mut other_value = a.clone();
+        other_value.get_mut(&path(&["y", "z"])).unwrap().value = 4;
+        assert_ne!(a, other_value);
+
+        let mut extra_child = a.clone();
+        extra_child.insert_at(&path(&["x"]), "w".to_string(), 0);
+        assert_ne!(a, extra_child);
+
+        let mut moved = a.clone();
+        let z = moved.remove_at(&path(&["y", "z"])).unwrap();
+        moved.add_child_node("z".to_string(), z);
+        assert_ne!(a, moved);
+    }
+
+    #[test]
+    fn equal_trees_hash_alike() {
+        let (a, b) = pair();
+        assert_eq!(hash_of(&a), hash_of(&b));
+
+        let set: HashSet<_> = [a.clone(), b].into_iter().collect();
+        assert_eq!(set.len(), 1);
+
+        let mut c = a.clone();
+        c.add_child("q".to_string(), 0);
+        assert_ne!(hash_of(&a), hash_of(&c));
+    }
+
+    #[test]
+    fn debug_shows_nested_nodes() {
+        let tree: AddressableTree<String, i32> = [ (path(&["a"]), 1) ].into_iter().collect();
+
+        assert_eq!(format!("{:?}", tree),
+                   r#"AddressableTree { key: "", value: 0, children: [AddressableTree { key: "a", value: 1, children: [] }] }"#);
+    }
+
+    #[test]
+    fn sizes_count_below_the_root() {
+        let (tree, _) = pair();
+        let root_only: AddressableTree<String, i32> = AddressableTree::empty();
+
+        assert!(root_only.is_empty());
+        assert_eq!(root_only.len(), 0);
+        assert!(!tree.is_empty());
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.depth(), 2);
+        assert_eq!(tree.leaf_count(), 2);
+    }
+
+    #[test]
+    fn pre_order_visits_parents_first() {
+        assert_eq!(paths(sample().pre_order()), ["", "a", "a/b", "a/c", "a/c/d", "e"]);
+    }
+
+    #[test]
+    fn post_order_visits_children_first() {
+        assert_eq!(paths(sample().post_order()), ["a/b", "a/c/d", "a/c", "a", "e", ""]);
+    }
+
+    #[test]
+    fn level_order_visits_level_by_level() {
+        assert_eq!(paths(sample().level_order()), ["", "a", "e", "a/b", "a/c", "a/c/d"]);
+    }
+
+    #[test]
+    fn iterators_start_from_the_node_they_are_called_on() {
+        let tree = sample();
+        let c = tree.get(&path(&["a", "c"])).unwrap();
+
+        assert_eq!(paths(c.pre_order()), ["", "d"]);
+        assert_eq!(paths(c.post_order()), ["d", ""]);
+    }
+
+    #[test]
+    fn pre_order_mut_edits_values_in_place() {
+        let mut tree = sample();
+
+        let mut visited = Vec::new();
+        for (path, key, value) in tree.pre_order_mut() {
+            assert_eq!(path.last().unwrap_or(key), key);
+            visited.push(path.join("/"));
+            *value *= 10;
+        }
+
+        assert_eq!(visited, ["", "a", "a/b", "a/c", "a/c/d", "e"]);
+        assert_eq!(tree.pre_order().map(|(_, node)| *node.get_value()).collect::<Vec<_>>(), [0, 10, 20, 30, 40, 50]);
+    }
+
+    #[test]
+    fn get_follows_paths() {
+        let mut tree = sample();
+
+        assert_eq!(tree.get(&[]).map(|node| *node.get_value()), Some(0));
+        assert_eq!(tree.get(&path(&["a", "c", "d"])).map(|node| *node.get_value()), Some(4));
+        assert!(tree.get(&path(&["a", "x"])).is_none());
+        assert!(tree.get(&path(&["a", "c", "d", "e"])).is_none());
+
+        tree.get_mut(&path(&["a", "b"])).unwrap().value = 20;
+        assert_eq!(tree.get(&path(&["a", "b"])).map(|node| *node.get_value()), Some(20));
+        assert!(tree.get_mut(&path(&["x"])).is_none());
+    }
+
+    #[test]
+    fn insert_at_needs_the_parent() {
+        let mut tree = sample();
+
+        assert_eq!(tree.insert_at(&[], "f".to_string(), 6).map(|node| *node.get_value()), Some(6));
+        assert_eq!(tree.insert_at(&path(&["a", "c"]), "g".to_string(), 7).map(|node| *node.get_value()), Some(7));
+        assert!(tree.insert_at(&path(&["x", "y"]), "h".to_string(), 8).is_none());
+        assert!(tree.get(&path(&["x"])).is_none());
+        assert_eq!(tree.len(), 7);
+
+        // replacing drops the old subtree
+        tree.insert_at(&[], "a".to_string(), 10);
+        assert!(tree.get(&path(&["a", "b"])).is_none());
+    }
+
+    #[test]
+    fn insert_at_creating_fills_in_parents() {
+        let mut tree = sample();
+
+        let made = tree.insert_at_creating(&path(&["a", "x", "y"]), "z".to_string(), 9, |key| key.len() as i32 * 100);
+        assert_eq!(made.get_value(), &9);
+        assert_eq!(tree.get(&path(&["a"])).map(|node| *node.get_value()), Some(1));
+        assert_eq!(tree.get(&path(&["a", "x"])).map(|node| *node.get_value()), Some(100));
+        assert_eq!(tree.get(&path(&["a", "x", "y", "z"])).map(|node| *node.get_value()), Some(9));
+    }
+
+    #[test]
+    fn remove_at_takes_the_subtree() {
+        let mut tree = sample();
+
+        assert!(tree.remove_at(&[]).is_none());
+        assert!(tree.remove_at(&path(&["a", "x"])).is_none());
+        assert!(tree.remove_at(&path(&["x", "c"])).is_none());
+
+        let c = tree.remove_at(&path(&["a", "c"])).unwrap();
+        assert_eq!(c.len(), 1);
+        assert!(tree.get(&path(&["a", "c"])).is_none());
+        assert_eq!(tree.len(), 3);
+    }
+
+    fn child_keys(tree: &AddressableTree<String, i32>) -> Vec<String> {
+        tree.get_children().iter().map(|child| child.get_key().clone()).collect()
+    }
+
+    #[test]
+    fn insertion_order_is_kept() {
+        let mut tree = AddressableTree::singleton("/".to_string(), 0);
+        for key in ["c", "a", "b"] {
+            tree.add_child(key.to_string(), 0);
+        }
+        assert_eq!(child_keys(&tree), ["c", "a", "b"]);
+
+        // replaced children keep their place, removed ones leave no gap
+        tree.add_child("a".to_string(), 1);
+        tree.replace("c".to_string(), 2);
+        assert_eq!(child_keys(&tree), ["c", "a", "b"]);
+
+        tree.remove_child(&"a".to_string());
+        tree.add_child("a".to_string(), 3);
+        assert_eq!(child_keys(&tree), ["c", "b", "a"]);
+        assert_eq!(tree.get(&path(&["b"])).map(|node| *node.get_value()), Some(0));
+    }
+
+    #[test]
+    fn sorted_order_applies_recursively() {
+        let mut tree: AddressableTree<String, i32> = [ (path(&["b", "z"]), 1), (path(&["b", "y"]), 2), (path(&["a"]), 3) ].into_iter().collect();
+        tree.set_order(ChildOrder::sorted());
+
+        assert_eq!(child_keys(&tree), ["a", "b"]);
+        assert_eq!(child_keys(tree.get(&path(&["b"])).unwrap()), ["y", "z"]);
+    }
+
+    #[test]
+    fn new_children_inherit_the_order() {
+        let mut tree: AddressableTree<String, i32> = [ (path(&["m"]), 1) ].into_iter().collect();
+        tree.set_order(ChildOrder::sorted());
+
+        tree.add_child("z".to_string(), 0);
+        tree.add_child("a".to_string(), 0);
+        tree.insert_at_creating(&path(&["m", "q"]), "c".to_string(), 0, |_| 0);
+        tree.insert_at(&path(&["m", "q"]), "b".to_string(), 0);
+        tree.insert_at(&path(&["m"]), "p".to_string(), 0);
+
+        assert_eq!(child_keys(&tree), ["a", "m", "z"]);
+        assert_eq!(child_keys(tree.get(&path(&["m"])).unwrap()), ["p", "q"]);
+        assert_eq!(child_keys(tree.get(&path(&["m", "q"])).unwrap()), ["b", "c"]);
+    }
+
+    #[test]
+    fn traversals_follow_the_order() {
+        let mut tree: AddressableTree<String, i32> = [ (path(&["b", "d"]), 1), (path(&["b", "c"]), 2), (path(&["a"]), 3) ].into_iter().collect();
+        let folded = |tree: &AddressableTree<String, i32>| tree.fold(&|node, mut keys: Vec<String>| { keys.push(node.get_key().clone()); keys }, Vec::new());
+
+        assert_eq!(paths(tree.pre_order()), ["", "b", "b/d", "b/c", "a"]);
+        assert_eq!(folded(&tree), ["d", "c", "b", "a", ""]);
+
+        tree.set_order(ChildOrder::sorted());
+        assert_eq!(paths(tree.pre_order()), ["", "a", "b", "b/c", "b/d"]);
+        assert_eq!(folded(&tree), ["a", "c", "d", "b", ""]);
+    }
+
+    #[test]
+    fn fold_pre_order_visits_parents_first() {
+        let keys = sample().fold_pre_order(&|node, mut keys: Vec<String>| { keys.push(node.get_key().clone()); keys }, Vec::new());
+
+        assert_eq!(keys, ["", "a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn fold_with_context_gives_depths_and_paths() {
+        let visited = sample().fold_with_context(&|node, depth, path, mut visited: Vec<(usize, String)>| {
+            assert_eq!(path.last().unwrap_or(node.get_key()), node.get_key());
+            visited.push((depth, path.join("/")));
+            visited
+        }, Vec::new());
+
+        assert_eq!(visited, [ (2, "a/b".to_string()), (3, "a/c/d".to_string()), (2, "a/c".to_string())
+                            , (1, "a".to_string()), (1, "e".to_string()), (0, String::new()) ]);
+    }
+
+    #[test]
+    fn try_fold_returns_the_first_error() {
+        let tree = sample();
+
+        let sum = tree.try_fold(&|node, acc: i32| Ok::<_, String>(acc + node.get_value()), 0);
+        assert_eq!(sum, Ok(15));
+
+        let failed = tree.try_fold(&|node, acc: i32| if *node.get_value() >= 3 { Err(node.get_key().clone()) } else { Ok(acc + 1) }, 0);
+        assert_eq!(failed, Err("d".to_string()));
+    }
+
+    #[test]
+    fn fold_until_stops_at_the_first_break() {
+        let tree = sample();
+        let visited = std::cell::Cell::new(0);
+
+        // post-order: b, d, c, a, e, root
+        let found = tree.fold_until(&|node, acc: i32| {
+            visited.set(visited.get() + 1);
+            if node.get_key() == "c" { ControlFlow::Break(acc) } else { ControlFlow::Continue(acc + node.get_value()) }
+        }, 0);
+
+        assert_eq!(found, ControlFlow::Break(6));
+        assert_eq!(visited.get(), 3);
+
+        let all = tree.fold_until(&|node, acc: i32| ControlFlow::<(), i32>::Continue(acc + node.get_value()), 0);
+        assert_eq!(all, ControlFlow::Continue(15));
+    }
+
+    #[test]
+    fn try_add_child_refuses_taken_keys() {
+        let mut tree = sample();
+
+        assert_eq!(tree.try_add_child("a".to_string(), 9), Err(TreeError::DuplicateKey("a".to_string())));
+        assert_eq!(tree.get(&path(&["a"])).map(|node| (*node.get_value(), node.len())), Some((1, 3)));
+
+        assert_eq!(tree.try_add_child("f".to_string(), 6), Ok(()));
+        assert_eq!(tree.get(&path(&["f"])).map(|node| *node.get_value()), Some(6));
+        assert_eq!(TreeError::DuplicateKey("a").to_string(), r#"child "a" already exists"#);
+    }
+
+    #[test]
+    fn insert_or_keep_keeps_the_existing_subtree() {
+        let mut tree = sample();
+
+        assert!(!tree.insert_or_keep("a".to_string(), 9));
+        assert_eq!(tree.get(&path(&["a"])).map(|node| *node.get_value()), Some(1));
+        assert!(tree.get(&path(&["a", "c", "d"])).is_some());
+
+        assert!(tree.insert_or_keep("f".to_string(), 6));
+        assert_eq!(tree.get(&path(&["f"])).map(|node| *node.get_value()), Some(6));
+    }
+
+    #[test]
+    fn replace_returns_the_old_subtree() {
+        let mut tree = sample();
+
+        let old = tree.replace("a".to_string(), 9).unwrap();
+        assert_eq!(old.get_value(), &1);
+        assert_eq!(paths(old.pre_order()), ["", "b", "c", "c/d"]);
+        assert_eq!(tree.get(&path(&["a"])).map(|node| (*node.get_value(), node.len())), Some((9, 0)));
+
+        assert!(tree.replace("f".to_string(), 6).is_none());
+    }
+
+    #[test]
+    fn try_remove_child_reports_missing_keys() {
+        let mut tree = sample();
+
+        assert_eq!(tree.try_remove_child(&"x".to_string()), Err(TreeError::MissingKey("x".to_string())));
+        assert_eq!(tree.len(), 5);
+
+        let e = tree.try_remove_child(&"e".to_string()).unwrap();
+        assert_eq!(e.get_value(), &5);
+        assert_eq!(tree.len(), 4);
+        assert_eq!(TreeError::MissingKey("x").to_string(), r#"no child "x""#);
+    }
+}
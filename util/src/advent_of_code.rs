@@ -1,28 +1,90 @@
-use std::{env, fs::File, io::{BufReader, Lines}};
+use std::env;
 
-use crate::files::read_lines;
+use crate::files::{read_normalized_lines, Input, InputLines, NormalizeOptions};
 
-pub fn redirect(part_1: fn(Lines<BufReader<File>>), part_2: fn(Lines<BufReader<File>>)) 
+// Solvers that work on the whole input at once, borrowing its lines (or bytes)
+// instead of receiving one allocated String per line.
+pub trait Solution {
+    fn part_1(input: &Input);
+    fn part_2(input: &Input);
+}
+
+// Extra subcommand besides the two parts, run with the input lines and every
+// argument given after the input filepath.
+pub type Command = (&'static str, fn(InputLines, &[String]));
+
+pub fn redirect(part_1: fn(InputLines), part_2: fn(InputLines))
 {
-    let args:Vec<String> = env::args().collect();
+    redirect_with(NormalizeOptions::default(), part_1, part_2);
+}
 
-    if args.len() < 2 {
-        println!("Use: cargo run <1|2> <input filepath>");
-        return;
-    }
+// same as redirect, with control over how the input gets normalized
+pub fn redirect_with(options: NormalizeOptions, part_1: fn(InputLines), part_2: fn(InputLines))
+{
+    redirect_with_commands(options, part_1, part_2, &[]);
+}
 
-    let part = args.get(1).expect("no part selected");
-    let filename = args.get(2).expect("no input file path given");
+// same as redirect_with, also accepting the given subcommands in place of a part
+pub fn redirect_with_commands( options: NormalizeOptions
+                             , part_1: fn(InputLines)
+                             , part_2: fn(InputLines)
+                             , commands: &[Command]
+                             ) {
+    let names: Vec<&str> = commands.iter().map(|(name, _)| *name).collect();
+    let Some((part, filename, args)) = parse_args(&names) else { return };
 
     // Read file
-    let lines = read_lines(filename).expect("error reading file");
+    let lines = read_normalized_lines(filename, &options).expect("error reading file");
 
     match part.as_str() {
         // Part 1
         "1" => part_1(lines),
         // Part 2
         "2" => part_2(lines),
+        // Subcommands
+        name => match commands.iter().find(|(command, _)| *command == name) {
+            Some((_, command)) => command(lines, &args),
+            // Error
+            None => println!("selected part is invalid"),
+        },
+    }
+}
+
+// same as redirect, for solvers that opted into zero-copy input
+pub fn solve<S: Solution>()
+{
+    solve_with::<S>(NormalizeOptions::default());
+}
+
+pub fn solve_with<S: Solution>(options: NormalizeOptions)
+{
+    let Some((part, filename, _)) = parse_args(&[]) else { return };
+
+    // Read file, once
+    let input = Input::read(filename, &options).expect("error reading file");
+
+    match part.as_str() {
+        // Part 1
+        "1" => S::part_1(&input),
+        // Part 2
+        "2" => S::part_2(&input),
         // Error
         _ => println!("selected part is invalid"),
     }
-}
\ No newline at end of file
+}
+
+// Part (or subcommand), input filepath and the remaining arguments, or None
+// after printing the usage when there aren't enough of them.
+fn parse_args(commands: &[&str]) -> Option<(String, String, Vec<String>)>
+{
+    let args:Vec<String> = env::args().collect();
+
+    if args.len() < 3 {
+        let names: Vec<&str> = ["1", "2"].into_iter().chain(commands.iter().copied()).collect();
+        let extra = if commands.is_empty() { "" } else { " [args]" };
+        println!("Use: cargo run <{}> <input filepath>{}", names.join("|"), extra);
+        return None;
+    }
+
+    Some((args[1].clone(), args[2].clone(), args[3..].to_vec()))
+}
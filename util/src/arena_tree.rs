@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+use crate::addressable_tree::AddressableTree;
+
+// Handle to a node of an ArenaTree. Handles of removed nodes are never reused,
+// but they are no longer valid either.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct NodeId(usize);
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    prev_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+    // children by key, so they can be addressed like in AddressableTree
+    index: HashMap<K, NodeId>,
+}
+
+// Tree whose nodes all live in a single Vec, linked to their parent, children
+// and siblings through NodeIds. Moving up to a parent is O(1), and nothing has
+// to be detached to walk down. Children are kept in insertion order.
+pub struct ArenaTree<K, V> {
+    // removed nodes leave a hole behind
+    nodes: Vec<Option<Node<K, V>>>,
+    // live nodes below the root
+    len: usize,
+}
+
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V> ArenaTree<K, V> {
+    pub fn new(key: K, value: V) -> ArenaTree<K, V> {
+        let root = Node { key
+                        , value
+                        , parent: None
+                        , first_child: None
+                        , last_child: None
+                        , prev_sibling: None
+                        , next_sibling: None
+                        , index: HashMap::new() };
+
+        ArenaTree { nodes: vec![Some(root)], len: 0 }
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    // Like AddressableTree, the root is the anchor of the tree: an empty tree
+    // is a root without children, and len counts the nodes below the root.
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        matches!(self.nodes.get(id.0), Some(Some(_)))
+    }
+
+    // Accessors panic when given the id of a removed node, like indexing would.
+
+    fn node(&self, id: NodeId) -> &Node<K, V> {
+        self.nodes[id.0].as_ref().expect("node was removed")
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node<K, V> {
+        self.nodes[id.0].as_mut().expect("node was removed")
+    }
+
+    pub fn get_key(&self, id: NodeId) -> &K {
+        &self.node(id).key
+    }
+
+    pub fn get_value(&self, id: NodeId) -> &V {
+        &self.node(id).value
+    }
+
+    pub fn get_value_mut(&mut self, id: NodeId) -> &mut V {
+        &mut self.node_mut(id).value
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    pub fn first_child(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).first_child
+    }
+
+    pub fn last_child(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).last_child
+    }
+
+    pub fn prev_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).prev_sibling
+    }
+
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).next_sibling
+    }
+
+    pub fn child(&self, id: NodeId, key: &K) -> Option<NodeId> {
+        self.node(id).index.get(key).copied()
+    }
+
+    pub fn children(&self, id: NodeId) -> Children<'_, K, V> {
+        Children { tree: self, next: self.first_child(id) }
+    }
+
+    // parent, grandparent and so on up to the root
+    pub fn ancestors(&self, id: NodeId) -> Ancestors<'_, K, V> {
+        Ancestors { tree: self, next: self.parent(id) }
+    }
+
+    // Appends a child to the node, returning its id. An existing child with the
+    // same key is removed first, along with its subtree.
+    pub fn add_child(&mut self, parent: NodeId, key: K, value: V) -> NodeId {
+        if let Some(existing) = self.child(parent, &key) {
+            self.remove(existing);
+        }
+
+        let id = NodeId(self.nodes.len());
+        let prev_sibling = self.last_child(parent);
+
+        self.nodes.push(Some(Node { key: key.clone()
+                                  , value
+                                  , parent: Some(parent)
+                                  , first_child: None
+                                  , last_child: None
+                                  , prev_sibling
+                                  , next_sibling: None
+                                  , index: HashMap::new() }));
+        self.len += 1;
+
+        match prev_sibling {
+            Some(prev) => self.node_mut(prev).next_sibling = Some(id),
+            None => self.node_mut(parent).first_child = Some(id),
+        }
+
+        let parent_node = self.node_mut(parent);
+        parent_node.last_child = Some(id);
+        parent_node.index.insert(key, id);
+
+        id
+    }
+
+    // Removes the node and its whole subtree, returning whether anything was
+    // removed. The root can't be removed.
+    pub fn remove(&mut self, id: NodeId) -> bool {
+        if !self.contains(id) {
+            return false;
+        }
+        let Some(parent) = self.parent(id) else { return false };
+
+        // unlink from parent and siblings
+        let (prev, next) = (self.prev_sibling(id), self.next_sibling(id));
+        match prev {
+            Some(prev) => self.node_mut(prev).next_sibling = next,
+            None => self.node_mut(parent).first_child = next,
+        }
+        match next {
+            Some(next) => self.node_mut(next).prev_sibling = prev,
+            None => self.node_mut(parent).last_child = prev,
+        }
+        let key = self.get_key(id).clone();
+        self.node_mut(parent).index.remove(&key);
+
+        // free the whole subtree
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            stack.extend(self.children(current));
+            self.nodes[current.0] = None;
+            self.len -= 1;
+        }
+
+        true
+    }
+}
+
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V> From<AddressableTree<K, V>> for ArenaTree<K, V> {
+    fn from(tree: AddressableTree<K, V>) -> Self {
+        let (key, value, children) = tree.into_parts();
+        let mut arena = ArenaTree::new(key, value);
+
+        let root = arena.root();
+        let mut stack: Vec<_> = children.into_iter().rev().map(|child| (root, child)).collect();
+        while let Some((parent, node)) = stack.pop() {
+            let (key, value, children) = node.into_parts();
+            let id = arena.add_child(parent, key, value);
+            stack.extend(children.into_iter().rev().map(|child| (id, child)));
+        }
+
+        arena
+    }
+}
+
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V> From<ArenaTree<K, V>> for AddressableTree<K, V> {
+    fn from(mut arena: ArenaTree<K, V>) -> Self {
+        let root = arena.root();
+        into_tree(&mut arena, root)
+    }
+}
+
+fn into_tree<K, V>(arena: &mut ArenaTree<K, V>, id: NodeId) -> AddressableTree<K, V>
+where K: Eq + core::hash::Hash + PartialEq + Clone {
+    let children: Vec<NodeId> = arena.children(id).collect();
+    let node = arena.nodes[id.0].take().expect("node was removed");
+
+    let mut tree = AddressableTree::singleton(node.key, node.value);
+    for child in children {
+        let child_tree = into_tree(arena, child);
+        tree.add_child_node(child_tree.get_key().clone(), child_tree);
+    }
+
+    tree
+}
+
+pub struct Children<'a, K, V> {
+    tree: &'a ArenaTree<K, V>,
+    next: Option<NodeId>,
+}
+
+impl <'a, K: Eq + core::hash::Hash + PartialEq + Clone, V> Iterator for Children<'a, K, V> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next?;
+        self.next = self.tree.next_sibling(current);
+        Some(current)
+    }
+}
+
+pub struct Ancestors<'a, K, V> {
+    tree: &'a ArenaTree<K, V>,
+    next: Option<NodeId>,
+}
+
+impl <'a, K: Eq + core::hash::Hash + PartialEq + Clone, V> Iterator for Ancestors<'a, K, V> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next?;
+        self.next = self.tree.parent(current);
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tree_view::TreeView;
+
+    use super::*;
+
+    #[test]
+    fn sizes_count_below_the_root_like_addressable_tree() {
+        let mut tree = ArenaTree::new("/", 0);
+        assert!(tree.is_empty());
+        assert_eq!(tree.len(), 0);
+
+        let root = tree.root();
+        let a = tree.add_child(root, "a", 1);
+        tree.add_child(a, "b", 2);
+        tree.add_child(root, "c", 3);
+        assert!(!tree.is_empty());
+        assert_eq!(tree.len(), 3);
+
+        let converted = AddressableTree::from(tree);
+        assert_eq!(converted.len(), 3);
+
+        let mut tree = ArenaTree::from(converted);
+        let a = tree.child(tree.root(), &"a").unwrap();
+        assert!(tree.remove(a));
+        assert_eq!(tree.len(), 1);
+    }
+}
@@ -0,0 +1,225 @@
+use std::ops::Add;
+
+use crate::{addressable_tree::AddressableTree, arena_tree::{ArenaTree, Children, NodeId}};
+
+// Monoid used to summarize subtrees: each value is measured into a summary,
+// and summaries are combined (associatively, with identity as neutral element).
+pub struct Monoid<V, S> {
+    pub identity: S,
+    pub measure: fn(&V) -> S,
+    pub combine: fn(&S, &S) -> S,
+}
+
+impl <V, S> Monoid<V, S> {
+    // summary of a node with the given value and children summaries
+    fn summarize<'a>(&self, value: &V, children: impl Iterator<Item = &'a S>) -> S
+    where S: 'a {
+        let own = (self.combine)(&self.identity, &(self.measure)(value));
+        children.fold(own, |acc, child| (self.combine)(&acc, child))
+    }
+}
+
+impl <V, S: Add<Output = S> + Default + Copy> Monoid<V, S> {
+    pub fn sum(measure: fn(&V) -> S) -> Monoid<V, S> {
+        Monoid { identity: S::default(), measure, combine: |a, b| *a + *b }
+    }
+}
+
+impl <V, S: Ord + Copy> Monoid<V, S> {
+    // identity should be the smallest value measure can give
+    pub fn max(identity: S, measure: fn(&V) -> S) -> Monoid<V, S> {
+        Monoid { identity, measure, combine: |a, b| *a.max(b) }
+    }
+}
+
+impl <V> Monoid<V, usize> {
+    pub fn count() -> Monoid<V, usize> {
+        Monoid { identity: 0, measure: |_| 1, combine: |a, b| a + b }
+    }
+}
+
+// ArenaTree where every node caches the summary of its whole subtree (itself
+// included). Summaries are refreshed along the ancestor path whenever a node is
+// added, removed or has its value changed, so they never need a full pass.
+pub struct AugmentedTree<K, V, S> {
+    tree: ArenaTree<K, (V, S)>,
+    monoid: Monoid<V, S>,
+}
+
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V, S: Clone> AugmentedTree<K, V, S> {
+    pub fn new(key: K, value: V, monoid: Monoid<V, S>) -> AugmentedTree<K, V, S> {
+        let summary = monoid.summarize(&value, std::iter::empty());
+        AugmentedTree { tree: ArenaTree::new(key, (value, summary)), monoid }
+    }
+
+    pub fn from_tree(tree: AddressableTree<K, V>, monoid: Monoid<V, S>) -> AugmentedTree<K, V, S> {
+        let summarized = tree.map_bottom_up(|_, value, children: &[AddressableTree<K, (V, S)>]| {
+            let summary = monoid.summarize(&value, children.iter().map(|child| &child.get_value().1));
+            (value, summary)
+        });
+
+        AugmentedTree { tree: ArenaTree::from(summarized), monoid }
+    }
+
+    pub fn into_tree(self) -> AddressableTree<K, V> {
+        AddressableTree::from(self.tree).map(|_, (value, _)| value)
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.tree.root()
+    }
+
+    // number of nodes below the root
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn get_key(&self, id: NodeId) -> &K {
+        self.tree.get_key(id)
+    }
+
+    pub fn get_value(&self, id: NodeId) -> &V {
+        &self.tree.get_value(id).0
+    }
+
+    // summary of the subtree rooted at the node
+    pub fn get_summary(&self, id: NodeId) -> &S {
+        &self.tree.get_value(id).1
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.tree.parent(id)
+    }
+
+    pub fn child(&self, id: NodeId, key: &K) -> Option<NodeId> {
+        self.tree.child(id, key)
+    }
+
+    pub fn children(&self, id: NodeId) -> Children<'_, K, (V, S)> {
+        self.tree.children(id)
+    }
+
+    pub fn set_value(&mut self, id: NodeId, value: V) {
+        self.tree.get_value_mut(id).0 = value;
+        self.refresh(Some(id));
+    }
+
+    pub fn add_child(&mut self, parent: NodeId, key: K, value: V) -> NodeId {
+        let summary = self.monoid.summarize(&value, std::iter::empty());
+        let id = self.tree.add_child(parent, key, (value, summary));
+        self.refresh(Some(parent));
+        id
+    }
+
+    pub fn remove(&mut self, id: NodeId) -> bool {
+        let parent = self.tree.parent(id);
+        let removed = self.tree.remove(id);
+        if removed {
+            self.refresh(parent);
+        }
+        removed
+    }
+
+    // recomputes the summaries of the node and all its ancestors
+    fn refresh(&mut self, from: Option<NodeId>) {
+        let mut next = from;
+
+        while let Some(id) = next {
+            let children = self.tree.children(id).map(|child| self.get_summary(child));
+            let summary = self.monoid.summarize(self.get_value(id), children);
+            self.tree.get_value_mut(id).1 = summary;
+
+            next = self.tree.parent(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tree_view::TreeView;
+
+    use super::*;
+
+    // a(2) holding b(3), which holds c(4), under a root of 1
+    fn chain() -> (AugmentedTree<&'static str, i32, i32>, [NodeId; 3]) {
+        let mut tree = AugmentedTree::new("/", 1, Monoid::sum(|value: &i32| *value));
+        let root = tree.root();
+        let a = tree.add_child(root, "a", 2);
+        let b = tree.add_child(a, "b", 3);
+        let c = tree.add_child(b, "c", 4);
+        (tree, [a, b, c])
+    }
+
+    fn summaries(tree: &AugmentedTree<&'static str, i32, i32>, ids: &[NodeId]) -> Vec<i32> {
+        ids.iter().map(|&id| *tree.get_summary(id)).collect()
+    }
+
+    #[test]
+    fn add_child_updates_every_ancestor() {
+        let (tree, [a, b, c]) = chain();
+
+        assert_eq!(summaries(&tree, &[tree.root(), a, b, c]), [10, 9, 7, 4]);
+    }
+
+    #[test]
+    fn set_value_updates_up_to_the_root() {
+        let (mut tree, [a, b, c]) = chain();
+        tree.set_value(b, 6);
+
+        assert_eq!(summaries(&tree, &[tree.root(), a, b, c]), [13, 12, 10, 4]);
+    }
+
+    #[test]
+    fn remove_subtracts_the_whole_subtree() {
+        let (mut tree, [a, b, _]) = chain();
+
+        assert!(tree.remove(b));
+        assert_eq!(summaries(&tree, &[tree.root(), a]), [3, 2]);
+
+        assert!(tree.remove(a));
+        assert_eq!(summaries(&tree, &[tree.root()]), [1]);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn readding_a_key_replaces_its_subtree() {
+        let (mut tree, _) = chain();
+        let root = tree.root();
+
+        let a = tree.add_child(root, "a", 5);
+        assert_eq!(summaries(&tree, &[root, a]), [6, 5]);
+        assert_eq!(tree.len(), 1);
+    }
+
+    // every summary of the tree built by from_tree, against the one computed
+    // from scratch on the same subtree
+    fn check_from_tree<S: Clone + PartialEq + std::fmt::Debug>( source: &AddressableTree<&'static str, i32>
+                                                             , monoid: fn() -> Monoid<i32, S>
+                                                             , expected: fn(&AddressableTree<&'static str, i32>) -> S
+                                                             ) {
+        let tree = AugmentedTree::from_tree(source.clone(), monoid());
+
+        let mut stack = vec![(tree.root(), Vec::new())];
+        while let Some((id, path)) = stack.pop() {
+            let subtree = source.get(&path).expect("same shape as the source");
+            assert_eq!(tree.get_summary(id), &expected(subtree), "at {:?}", path);
+
+            for child in tree.children(id) {
+                stack.push((child, [path.as_slice(), &[*tree.get_key(child)]].concat()));
+            }
+        }
+    }
+
+    #[test]
+    fn from_tree_matches_summaries_from_scratch() {
+        let source: AddressableTree<&str, i32> = [ (vec!["a"], 2), (vec!["a", "b"], 7), (vec!["a", "b", "c"], 4), (vec!["d"], 5), (vec!["d", "e"], 1) ].into_iter().collect();
+
+        check_from_tree(&source, || Monoid::sum(|value: &i32| *value), |subtree| subtree.fold(&|node, acc| acc + node.get_value(), 0));
+        check_from_tree(&source, || Monoid::max(i32::MIN, |value: &i32| *value), |subtree| subtree.fold(&|node, acc: i32| acc.max(*node.get_value()), i32::MIN));
+        check_from_tree(&source, Monoid::count, |subtree| subtree.len() + 1);
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fs::File, io::{self, BufRead}, path::Path};
+use std::{fmt, fs::{self, File}, io::{self, BufRead}, path::Path};
 
 // from https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html
 // The output is wrapped in a Result to allow matching on errors.
@@ -7,4 +7,243 @@ pub fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
 where P: AsRef<Path>, {
     let file = File::open(filename)?;
     Ok(io::BufReader::new(file).lines())
-}
\ No newline at end of file
+}
+
+// Lines of an input after normalization, as handed to the solvers.
+pub type InputLines = io::Lines<io::Cursor<String>>;
+
+// Reads the whole file, normalizes it and returns an Iterator over its lines.
+// Every change made to the input is reported as a warning on stderr.
+pub fn read_normalized_lines<P>(filename: P, options: &NormalizeOptions) -> io::Result<InputLines>
+where P: AsRef<Path>, {
+    let text = fs::read_to_string(filename)?;
+    let (normalized, changes) = normalize(&text, options);
+
+    warn(&changes);
+
+    Ok(io::Cursor::new(normalized).lines())
+}
+
+fn warn(changes: &[Normalization]) {
+    for change in changes {
+        eprintln!("warning: {}", change);
+    }
+}
+
+// Whole input kept in a single buffer, read (and normalized) once.
+// Lines and bytes are borrowed from it instead of being allocated one by one.
+pub struct Input {
+    text: String,
+}
+
+impl Input {
+    pub fn read<P>(filename: P, options: &NormalizeOptions) -> io::Result<Input>
+    where P: AsRef<Path>, {
+        let text = fs::read_to_string(filename)?;
+
+        // only pay for normalization when there is something to normalize
+        if is_normalized(&text, options) {
+            return Ok(Input { text });
+        }
+
+        let (normalized, changes) = normalize(&text, options);
+        warn(&changes);
+
+        Ok(Input { text: normalized })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        self.text.as_bytes()
+    }
+
+    pub fn lines(&self) -> std::str::Lines<'_> {
+        self.text.lines()
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct NormalizeOptions {
+    // indices of the blocks (groups of lines separated by blank lines) that
+    // have a fixed-width layout: each of their lines is padded with spaces up
+    // to the width of the block's longest line, restoring trailing spaces
+    // stripped by an editor
+    pub fixed_width_blocks: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Normalization {
+    ByteOrderMarkRemoved,
+    CarriageReturnsRemoved(usize),
+    TrailingWhitespaceTrimmed(usize),
+    LinesPadded(usize),
+}
+
+impl fmt::Display for Normalization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Normalization::ByteOrderMarkRemoved => write!(f, "removed byte order mark"),
+            Normalization::CarriageReturnsRemoved(n) => write!(f, "removed {} carriage return(s)", n),
+            Normalization::TrailingWhitespaceTrimmed(n) => write!(f, "trimmed trailing whitespace on {} line(s)", n),
+            Normalization::LinesPadded(n) => write!(f, "padded {} line(s) to their block width", n),
+        }
+    }
+}
+
+// Cheap check for text that normalize would leave untouched (line-wise).
+fn is_normalized(text: &str, options: &NormalizeOptions) -> bool {
+    options.fixed_width_blocks.is_empty()
+        && !text.starts_with('\u{feff}')
+        && !text.contains('\r')
+        && text.lines().all(|line| line.len() == line.trim_end().len())
+}
+
+// Strips the BOM, carriage returns and trailing whitespace, optionally pads
+// fixed-width blocks and makes sure the text ends with a newline.
+// Returns the normalized text along with the list of changes made to its
+// lines (a missing final newline leaves the lines as they are, so it isn't
+// one of them).
+pub fn normalize(text: &str, options: &NormalizeOptions) -> (String, Vec<Normalization>) {
+    let mut changes = Vec::new();
+
+    // byte order mark
+    let text = match text.strip_prefix('\u{feff}') {
+        Some(stripped) => {
+            changes.push(Normalization::ByteOrderMarkRemoved);
+            stripped
+        },
+        None => text,
+    };
+
+    // carriage returns (both CRLF and lone CR)
+    let carriage_returns = text.matches('\r').count();
+    if carriage_returns > 0 {
+        changes.push(Normalization::CarriageReturnsRemoved(carriage_returns));
+    }
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+
+    let original: Vec<&str> = text.lines().collect();
+    let mut lines: Vec<String> = original.iter().map(|line| line.trim_end().to_string()).collect();
+
+    // restore padding, block by block
+    let mut start = 0;
+    let mut block = 0;
+    while start < lines.len() {
+        let end = (start..lines.len()).find(|&i| lines[i].is_empty()).unwrap_or(lines.len());
+
+        if options.fixed_width_blocks.contains(&block) {
+            let width = lines[start..end].iter().map(|line| line.chars().count()).max().unwrap_or(0);
+
+            for line in &mut lines[start..end] {
+                let padding = width - line.chars().count();
+                line.extend(std::iter::repeat_n(' ', padding));
+            }
+        }
+
+        start = end + 1;
+        block += 1;
+    }
+
+    // compare against the original lines, padding that was put back is not a change
+    let trimmed = lines.iter().zip(&original).filter(|(new, old)| new.len() < old.len()).count();
+    let padded = lines.iter().zip(&original).filter(|(new, old)| new.len() > old.len()).count();
+    if trimmed > 0 {
+        changes.push(Normalization::TrailingWhitespaceTrimmed(trimmed));
+    }
+    if padded > 0 {
+        changes.push(Normalization::LinesPadded(padded));
+    }
+
+    let mut normalized = String::with_capacity(text.len() + 1);
+    for line in lines {
+        normalized.push_str(&line);
+        normalized.push('\n');
+    }
+
+    (normalized, changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed_width(blocks: &[usize]) -> NormalizeOptions {
+        NormalizeOptions { fixed_width_blocks: blocks.to_vec() }
+    }
+
+    #[test]
+    fn normalized_text_is_left_alone() {
+        let (text, changes) = normalize("a\nb\n", &NormalizeOptions::default());
+
+        assert_eq!(text, "a\nb\n");
+        assert!(changes.is_empty());
+        assert!(is_normalized("a\nb\n", &NormalizeOptions::default()));
+    }
+
+    #[test]
+    fn byte_order_mark_is_removed() {
+        let (text, changes) = normalize("\u{feff}a\n", &NormalizeOptions::default());
+
+        assert_eq!(text, "a\n");
+        assert_eq!(changes, vec![Normalization::ByteOrderMarkRemoved]);
+    }
+
+    #[test]
+    fn carriage_returns_are_removed() {
+        // CRLF and lone CR alike
+        let (text, changes) = normalize("a\r\nb\rc\r\n", &NormalizeOptions::default());
+
+        assert_eq!(text, "a\nb\nc\n");
+        assert_eq!(changes, vec![Normalization::CarriageReturnsRemoved(3)]);
+    }
+
+    #[test]
+    fn trailing_whitespace_is_trimmed() {
+        let (text, changes) = normalize("a  \nb\t\nc\n", &NormalizeOptions::default());
+
+        assert_eq!(text, "a\nb\nc\n");
+        assert_eq!(changes, vec![Normalization::TrailingWhitespaceTrimmed(2)]);
+    }
+
+    #[test]
+    fn final_newline_is_added_silently() {
+        let (text, changes) = normalize("a\nb", &NormalizeOptions::default());
+
+        assert_eq!(text, "a\nb\n");
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn stripped_fixed_width_block_is_padded_back() {
+        // day5's crate drawing, with the trailing spaces an editor strips
+        let input = "    [D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3\n\nmove 1 from 2 to 1\n";
+        let (text, changes) = normalize(input, &fixed_width(&[0]));
+
+        assert_eq!(text, "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n\nmove 1 from 2 to 1\n");
+        assert_eq!(changes, vec![Normalization::LinesPadded(3)]);
+    }
+
+    #[test]
+    fn intact_fixed_width_block_is_not_a_change() {
+        let input = "    [D]    \n[Z] [M] [P]\n\nmove 1 from 2 to 1  \n";
+        let (text, changes) = normalize(input, &fixed_width(&[0]));
+
+        // only the trailing spaces outside the block go
+        assert_eq!(text, "    [D]    \n[Z] [M] [P]\n\nmove 1 from 2 to 1\n");
+        assert_eq!(changes, vec![Normalization::TrailingWhitespaceTrimmed(1)]);
+    }
+
+    #[test]
+    fn all_changes_are_reported() {
+        let (text, changes) = normalize("\u{feff}a \r\nb\r\n", &NormalizeOptions::default());
+
+        assert_eq!(text, "a\nb\n");
+        assert_eq!(changes, vec![ Normalization::ByteOrderMarkRemoved
+                                , Normalization::CarriageReturnsRemoved(2)
+                                , Normalization::TrailingWhitespaceTrimmed(1) ]);
+        assert_eq!(changes[1].to_string(), "removed 2 carriage return(s)");
+    }
+}
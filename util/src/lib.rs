@@ -1,6 +1,16 @@
 pub mod files;
 pub mod addressable_tree;
+pub mod tree_view;
 pub mod tree_navigator;
+pub mod arena_tree;
+pub mod augmented_tree;
+pub mod persistent_tree;
+pub mod tree_render;
+pub mod tree_diff;
+pub mod tree_query;
+pub mod tree_parallel;
+#[cfg(feature = "json")]
+pub mod tree_json;
 pub mod advent_of_code;
 
 
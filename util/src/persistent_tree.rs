@@ -0,0 +1,222 @@
+use std::rc::Rc;
+
+use crate::{addressable_tree::AddressableTree, tree_view::TreeView};
+
+// Immutable tree whose subtrees are shared between versions. Every update
+// returns a new version, rebuilding only the nodes along the updated path and
+// sharing every other branch with the previous version, so keeping old
+// versions around is cheap. Children are kept in insertion order.
+//
+// Queries (get, sizes, folds, iterators) come from TreeView, like for
+// AddressableTree.
+pub struct PersistentTree<K, V> {
+    node: Rc<Node<K, V>>,
+}
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    children: Vec<PersistentTree<K, V>>,
+}
+
+// cheap, the whole tree is shared
+impl <K, V> Clone for PersistentTree<K, V> {
+    fn clone(&self) -> Self {
+        PersistentTree { node: Rc::clone(&self.node) }
+    }
+}
+
+impl <K: Eq + Clone, V: Clone> PersistentTree<K, V> {
+    pub fn singleton(key: K, value: V) -> PersistentTree<K, V> {
+        PersistentTree::from_node(key, value, Vec::new())
+    }
+
+    fn from_node(key: K, value: V, children: Vec<PersistentTree<K, V>>) -> PersistentTree<K, V> {
+        PersistentTree { node: Rc::new(Node { key, value, children }) }
+    }
+
+    pub fn get_key(&self) -> &K {
+        &self.node.key
+    }
+
+    pub fn get_value(&self) -> &V {
+        &self.node.value
+    }
+
+    pub fn get_children(&self) -> Vec<&PersistentTree<K, V>> {
+        self.node.children.iter().collect()
+    }
+
+    // whether both versions share this very node
+    pub fn ptr_eq(&self, other: &PersistentTree<K, V>) -> bool {
+        Rc::ptr_eq(&self.node, &other.node)
+    }
+
+    fn position(&self, key: &K) -> Option<usize> {
+        self.node.children.iter().position(|child| child.get_key() == key)
+    }
+
+    // -- updates, each returning a new version
+
+    pub fn with_value(&self, value: V) -> PersistentTree<K, V> {
+        PersistentTree::from_node(self.get_key().clone(), value, self.node.children.clone())
+    }
+
+    // Adds a child, replacing the existing one with the same key (and its subtree).
+    pub fn with_child(&self, key: K, value: V) -> PersistentTree<K, V> {
+        self.with_child_node(PersistentTree::singleton(key, value))
+    }
+
+    pub fn with_child_node(&self, child: PersistentTree<K, V>) -> PersistentTree<K, V> {
+        let mut children = self.node.children.clone();
+
+        match self.position(child.get_key()) {
+            Some(i) => children[i] = child,
+            None => children.push(child),
+        }
+
+        PersistentTree::from_node(self.get_key().clone(), self.get_value().clone(), children)
+    }
+
+    pub fn without_child(&self, key: &K) -> Option<PersistentTree<K, V>> {
+        let mut children = self.node.children.clone();
+        children.remove(self.position(key)?);
+
+        Some(PersistentTree::from_node(self.get_key().clone(), self.get_value().clone(), children))
+    }
+
+    // Applies an update to the node at path, rebuilding its ancestors.
+    // None if there is no such node, or if the update itself gives None.
+    pub fn update_at<F>(&self, path: &[K], update: F) -> Option<PersistentTree<K, V>>
+    where F: FnOnce(&PersistentTree<K, V>) -> Option<PersistentTree<K, V>> {
+        let Some((key, rest)) = path.split_first() else {
+            return update(self);
+        };
+
+        let i = self.position(key)?;
+        let child = self.node.children[i].update_at(rest, update)?;
+
+        let mut children = self.node.children.clone();
+        children[i] = child;
+
+        Some(PersistentTree::from_node(self.get_key().clone(), self.get_value().clone(), children))
+    }
+
+    pub fn set_at(&self, path: &[K], value: V) -> Option<PersistentTree<K, V>> {
+        self.update_at(path, |node| Some(node.with_value(value)))
+    }
+
+    pub fn insert_at(&self, path: &[K], key: K, value: V) -> Option<PersistentTree<K, V>> {
+        self.update_at(path, |node| Some(node.with_child(key, value)))
+    }
+
+    // The node this is called on can't remove itself, so the empty path gives None.
+    pub fn remove_at(&self, path: &[K]) -> Option<PersistentTree<K, V>> {
+        let (key, parent_path) = path.split_last()?;
+        self.update_at(parent_path, |node| node.without_child(key))
+    }
+}
+
+impl <K: Eq + Clone, V: Clone> TreeView for PersistentTree<K, V> {
+    type Key = K;
+    type Value = V;
+
+    fn key(&self) -> &K {
+        &self.node.key
+    }
+
+    fn value(&self) -> &V {
+        &self.node.value
+    }
+
+    fn children(&self) -> &[PersistentTree<K, V>] {
+        &self.node.children
+    }
+}
+
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V: Clone> From<AddressableTree<K, V>> for PersistentTree<K, V> {
+    fn from(tree: AddressableTree<K, V>) -> Self {
+        let (key, value, children) = tree.into_parts();
+        let children = children.into_iter().map(PersistentTree::from).collect();
+
+        PersistentTree::from_node(key, value, children)
+    }
+}
+
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V: Clone> From<&PersistentTree<K, V>> for AddressableTree<K, V> {
+    fn from(tree: &PersistentTree<K, V>) -> Self {
+        let mut result = AddressableTree::singleton(tree.get_key().clone(), tree.get_value().clone());
+
+        for child in tree.get_children() {
+            result.add_child_node(child.get_key().clone(), AddressableTree::from(child));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::*;
+
+    // /a/b, /c
+    fn sample() -> PersistentTree<&'static str, i32> {
+        PersistentTree::singleton("/", 0).insert_at(&[], "a", 1)
+                                         .and_then(|t| t.insert_at(&["a"], "b", 2))
+                                         .and_then(|t| t.insert_at(&[], "c", 3))
+                                         .unwrap()
+    }
+
+    fn paths<'a>(nodes: impl Iterator<Item = (Vec<&'a str>, &'a PersistentTree<&'a str, i32>)>) -> Vec<String> {
+        nodes.map(|(path, _)| path.join("/")).collect()
+    }
+
+    #[test]
+    fn updates_share_untouched_subtrees() {
+        let old = sample();
+        let new = old.set_at(&["c"], 30).unwrap();
+
+        assert_eq!(old.get(&["c"]).map(|node| *node.get_value()), Some(3));
+        assert_eq!(new.get(&["c"]).map(|node| *node.get_value()), Some(30));
+        assert!(old.get(&["a"]).unwrap().ptr_eq(new.get(&["a"]).unwrap()));
+        assert!(!old.ptr_eq(&new));
+    }
+
+    #[test]
+    fn sizes_count_below_the_root() {
+        let tree = sample();
+
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.depth(), 2);
+        assert_eq!(tree.leaf_count(), 2);
+        assert!(PersistentTree::singleton("/", 0).is_empty());
+    }
+
+    #[test]
+    fn iterators_visit_in_order() {
+        let tree = sample();
+
+        assert_eq!(paths(tree.pre_order()), vec!["", "a", "a/b", "c"]);
+        assert_eq!(paths(tree.post_order()), vec!["a/b", "a", "c", ""]);
+        assert_eq!(paths(tree.level_order()), vec!["", "a", "c", "a/b"]);
+    }
+
+    #[test]
+    fn folds_agree_with_addressable_tree() {
+        let tree = sample();
+        let addressable = AddressableTree::from(&tree);
+
+        let keys = |node: &PersistentTree<&'static str, i32>, mut acc: Vec<&'static str>| { acc.push(*node.get_key()); acc };
+        assert_eq!(tree.fold(&keys, Vec::new()), vec!["b", "a", "c", "/"]);
+        assert_eq!(tree.fold_pre_order(&keys, Vec::new()), vec!["/", "a", "b", "c"]);
+
+        let depths = tree.fold_with_context(&|_, depth, _, acc| acc + depth, 0);
+        assert_eq!(depths, addressable.fold_with_context(&|_, depth, _, acc| acc + depth, 0));
+
+        let found = tree.fold_until(&|node, acc| if *node.get_value() == 1 { ControlFlow::Break(acc) } else { ControlFlow::Continue(acc + 1) }, 0);
+        assert_eq!(found, ControlFlow::Break(1));
+        assert_eq!(tree.try_fold(&|node, acc| if *node.get_value() < 0 { Err(()) } else { Ok(acc + node.get_value()) }, 0), Ok(6));
+    }
+}
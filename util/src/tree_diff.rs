@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use crate::{addressable_tree::AddressableTree, tree_view::TreeView};
+
+// Difference between two trees, addressed by the path of keys leading to the
+// node (the roots being at the empty path). Added and removed nodes carry their
+// whole subtree, which is not detailed any further.
+pub enum TreeChange<'a, K, V> {
+    Added { path: Vec<K>, node: &'a AddressableTree<K, V> },
+    Removed { path: Vec<K>, node: &'a AddressableTree<K, V> },
+    Changed { path: Vec<K>, old: &'a V, new: &'a V },
+}
+
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V> AddressableTree<K, V> {
+    // Changes leading from this tree to the other one, in pre-order.
+    pub fn diff<'a>(&'a self, other: &'a AddressableTree<K, V>) -> Vec<TreeChange<'a, K, V>>
+    where V: PartialEq {
+        let mut changes = Vec::new();
+        let mut path = Vec::new();
+
+        diff_nodes(self, other, &mut path, &mut changes);
+
+        changes
+    }
+
+    // Combines both trees: nodes found in only one of them are kept as they
+    // are, nodes found in both get their values combined by resolve (given the
+    // node's path, this tree's value and the other's) and their children merged.
+    // Children keep this tree's order, followed by those only the other has.
+    pub fn merge<F>(self, other: AddressableTree<K, V>, mut resolve: F) -> AddressableTree<K, V>
+    where F: FnMut(&[K], V, V) -> V {
+        let mut path = Vec::new();
+        merge_nodes(self, other, &mut path, &mut resolve)
+    }
+}
+
+fn diff_nodes<'a, K, V>( old: &'a AddressableTree<K, V>
+                       , new: &'a AddressableTree<K, V>
+                       , path: &mut Vec<K>
+                       , changes: &mut Vec<TreeChange<'a, K, V>>
+                       )
+where K: Eq + core::hash::Hash + PartialEq + Clone, V: PartialEq {
+    if old.get_value() != new.get_value() {
+        changes.push(TreeChange::Changed { path: path.clone(), old: old.get_value(), new: new.get_value() });
+    }
+
+    for old_child in old.get_children() {
+        path.push(old_child.get_key().clone());
+
+        match new.get(std::slice::from_ref(old_child.get_key())) {
+            Some(new_child) => diff_nodes(old_child, new_child, path, changes),
+            None => changes.push(TreeChange::Removed { path: path.clone(), node: old_child }),
+        }
+
+        path.pop();
+    }
+
+    for new_child in new.get_children() {
+        if old.get(std::slice::from_ref(new_child.get_key())).is_none() {
+            let mut child_path = path.clone();
+            child_path.push(new_child.get_key().clone());
+            changes.push(TreeChange::Added { path: child_path, node: new_child });
+        }
+    }
+}
+
+fn merge_nodes<K, V, F>( tree: AddressableTree<K, V>
+                       , other: AddressableTree<K, V>
+                       , path: &mut Vec<K>
+                       , resolve: &mut F
+                       ) -> AddressableTree<K, V>
+where K: Eq + core::hash::Hash + PartialEq + Clone, F: FnMut(&[K], V, V) -> V {
+    let order = tree.get_order();
+    let (key, value, children) = tree.into_parts();
+    let (_, other_value, other_children) = other.into_parts();
+
+    let value = resolve(path, value, other_value);
+
+    // other's children, looked up by key while keeping their order
+    let positions: HashMap<K, usize> = other_children.iter()
+                                                     .enumerate()
+                                                     .map(|(i, child)| (child.get_key().clone(), i))
+                                                     .collect();
+    let mut other_children: Vec<Option<AddressableTree<K, V>>> = other_children.into_iter().map(Some).collect();
+
+    let mut merged = Vec::new();
+    for child in children {
+        let other_child = positions.get(child.get_key()).and_then(|&i| other_children[i].take());
+
+        match other_child {
+            Some(other_child) => {
+                path.push(child.get_key().clone());
+                merged.push(merge_nodes(child, other_child, path, resolve));
+                path.pop();
+            },
+            None => merged.push(child),
+        }
+    }
+    merged.extend(other_children.into_iter().flatten());
+
+    AddressableTree::from_parts(key, value, order, merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::addressable_tree::ChildOrder;
+
+    use super::*;
+
+    fn tree(nodes: &[(&[&'static str], i32)]) -> AddressableTree<&'static str, i32> {
+        nodes.iter().map(|(path, value)| (path.to_vec(), *value)).collect()
+    }
+
+    fn describe(changes: &[TreeChange<'_, &str, i32>]) -> Vec<String> {
+        changes.iter()
+               .map(|change| match change {
+                   TreeChange::Added { path, node } => format!("+ {} ({} below)", path.join("/"), node.len()),
+                   TreeChange::Removed { path, node } => format!("- {} ({} below)", path.join("/"), node.len()),
+                   TreeChange::Changed { path, old, new } => format!("~ {}: {} -> {}", path.join("/"), old, new),
+               })
+               .collect()
+    }
+
+    fn child_keys(tree: &AddressableTree<&'static str, i32>) -> Vec<&'static str> {
+        tree.get_children().iter().map(|child| *child.get_key()).collect()
+    }
+
+    #[test]
+    fn diff_of_equal_trees_is_empty() {
+        let old = tree(&[ (&["a"], 1), (&["a", "b"], 2) ]);
+
+        assert!(old.diff(&old.clone()).is_empty());
+    }
+
+    #[test]
+    fn diff_lists_changes_in_pre_order() {
+        let old = tree(&[ (&["a"], 1), (&["a", "b"], 2), (&["c"], 3), (&["c", "d"], 4), (&["e"], 5) ]);
+        let new = tree(&[ (&["a"], 1), (&["a", "b"], 20), (&["e"], 6), (&["f"], 7), (&["f", "g"], 8) ]);
+
+        // whole subtrees come and go at their top only
+        assert_eq!(describe(&old.diff(&new)), [ "~ a/b: 2 -> 20"
+                                              , "- c (1 below)"
+                                              , "~ e: 5 -> 6"
+                                              , "+ f (1 below)" ]);
+        assert_eq!(describe(&new.diff(&old)), [ "~ a/b: 20 -> 2"
+                                              , "~ e: 6 -> 5"
+                                              , "- f (1 below)"
+                                              , "+ c (1 below)" ]);
+    }
+
+    #[test]
+    fn diff_sees_the_roots() {
+        let old = tree(&[ (&[], 1) ]);
+        let new = tree(&[ (&[], 2) ]);
+
+        assert_eq!(describe(&old.diff(&new)), ["~ : 1 -> 2"]);
+    }
+
+    #[test]
+    fn merge_resolves_common_nodes_with_their_paths() {
+        let a = tree(&[ (&["x"], 1), (&["x", "y"], 2), (&["only_a"], 3) ]);
+        let b = tree(&[ (&["x"], 10), (&["x", "y"], 20), (&["only_b"], 30) ]);
+
+        let mut resolved = Vec::new();
+        let merged = a.merge(b, |path, mine, theirs| {
+            resolved.push(path.join("/"));
+            mine + theirs
+        });
+
+        assert_eq!(resolved, ["", "x", "x/y"]);
+        assert_eq!(merged, tree(&[ (&["x"], 11), (&["x", "y"], 22), (&["only_a"], 3), (&["only_b"], 30) ]));
+    }
+
+    #[test]
+    fn merge_keeps_this_order_then_the_others_extras() {
+        let a = tree(&[ (&["c"], 0), (&["a"], 0) ]);
+        let b = tree(&[ (&["z"], 0), (&["a"], 0), (&["b"], 0) ]);
+
+        let merged = a.merge(b, |_, mine, _| mine);
+        assert_eq!(child_keys(&merged), ["c", "a", "z", "b"]);
+    }
+
+    #[test]
+    fn merge_keeps_a_sorted_order() {
+        let mut a = tree(&[ (&["c"], 0), (&["a"], 0), (&["a", "y"], 0) ]);
+        a.set_order(ChildOrder::sorted());
+        let b = tree(&[ (&["z"], 0), (&["b"], 0), (&["a", "x"], 0) ]);
+
+        let merged = a.merge(b, |_, mine, _| mine);
+        assert_eq!(child_keys(&merged), ["a", "b", "c", "z"]);
+        assert_eq!(child_keys(merged.get(&["a"]).unwrap()), ["x", "y"]);
+    }
+}
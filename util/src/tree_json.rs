@@ -0,0 +1,128 @@
+use std::fmt;
+
+use serde::{de::{self, DeserializeOwned}, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::addressable_tree::{AddressableTree, TreeError};
+
+// Trees are encoded as nested objects:
+//
+// { "key": "/", "value": ..., "children": [ { "key": "a", ... }, ... ] }
+//
+// Children keep their order, but the ordering policy itself (a comparator) is
+// not encoded: decoded trees are ordered by insertion, i.e. as in the JSON.
+
+impl <K, V> Serialize for AddressableTree<K, V>
+where K: Eq + core::hash::Hash + PartialEq + Clone + Serialize, V: Serialize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut node = serializer.serialize_struct("AddressableTree", 3)?;
+        node.serialize_field("key", self.get_key())?;
+        node.serialize_field("value", self.get_value())?;
+        node.serialize_field("children", &self.get_children())?;
+        node.end()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "AddressableTree")]
+struct Node<K, V> {
+    key: K,
+    value: V,
+    #[serde(default = "Vec::new")]
+    children: Vec<Node<K, V>>,
+}
+
+// siblings sharing a key are rejected rather than replacing one another
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V> TryFrom<Node<K, V>> for AddressableTree<K, V> {
+    type Error = TreeError<K>;
+
+    fn try_from(node: Node<K, V>) -> Result<Self, Self::Error> {
+        let mut tree = AddressableTree::singleton(node.key, node.value);
+
+        for child in node.children {
+            let child_tree = AddressableTree::try_from(child)?;
+            tree.try_add_child_node(child_tree.get_key().clone(), child_tree)?;
+        }
+
+        Ok(tree)
+    }
+}
+
+impl <'de, K, V> Deserialize<'de> for AddressableTree<K, V>
+where K: Eq + core::hash::Hash + PartialEq + Clone + fmt::Debug + Deserialize<'de>, V: Deserialize<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let node = Node::deserialize(deserializer)?;
+        AddressableTree::try_from(node).map_err(de::Error::custom)
+    }
+}
+
+impl <K, V> AddressableTree<K, V>
+where K: Eq + core::hash::Hash + PartialEq + Clone + Serialize, V: Serialize {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn to_json_pretty(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl <K, V> AddressableTree<K, V>
+where K: Eq + core::hash::Hash + PartialEq + Clone + fmt::Debug + DeserializeOwned, V: DeserializeOwned {
+    pub fn from_json(json: &str) -> serde_json::Result<AddressableTree<K, V>> {
+        serde_json::from_str(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tree_view::TreeView;
+
+    use super::*;
+
+    fn sample() -> AddressableTree<String, i32> {
+        let mut tree = AddressableTree::singleton("/".to_string(), 0);
+        tree.add_child("b".to_string(), 2);
+        tree.add_child("a".to_string(), 1);
+        tree.get_mut(&["a".to_string()]).unwrap().add_child("c".to_string(), 3);
+        tree
+    }
+
+    fn keys(tree: &AddressableTree<String, i32>) -> Vec<&String> {
+        tree.get_children().iter().map(|child| child.get_key()).collect()
+    }
+
+    #[test]
+    fn round_trip_keeps_tree_and_child_order() {
+        let tree = sample();
+
+        for json in [tree.to_json().unwrap(), tree.to_json_pretty().unwrap()] {
+            let decoded: AddressableTree<String, i32> = AddressableTree::from_json(&json).unwrap();
+
+            assert_eq!(decoded, tree);
+            // equality ignores order, so check it separately
+            assert_eq!(keys(&decoded), vec!["b", "a"]);
+        }
+    }
+
+    #[test]
+    fn encoding_nests_children() {
+        let json = sample().to_json().unwrap();
+
+        assert_eq!(json, r#"{"key":"/","value":0,"children":[{"key":"b","value":2,"children":[]},{"key":"a","value":1,"children":[{"key":"c","value":3,"children":[]}]}]}"#);
+    }
+
+    #[test]
+    fn missing_children_decode_as_leaves() {
+        let tree: AddressableTree<String, i32> = AddressableTree::from_json(r#"{"key":"/","value":0}"#).unwrap();
+
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn duplicate_sibling_keys_are_rejected() {
+        let json = r#"{"key":"/","value":0,"children":[{"key":"a","value":1},{"key":"a","value":2}]}"#;
+        let error = AddressableTree::<String, i32>::from_json(json).unwrap_err();
+
+        assert!(error.to_string().contains(r#"child "a" already exists"#), "{}", error);
+    }
+}
@@ -1,53 +1,517 @@
-use crate::addressable_tree::AddressableTree;
+use std::{collections::HashMap, fmt};
 
+use crate::{addressable_tree::AddressableTree, tree_view::TreeView};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum NavigationError<K> {
+    // the node at path (from the root) has no child with this key
+    MissingChild { path: Vec<K>, key: K },
+    // already at the root, with no parent to get out to
+    AtRoot,
+    // no move recorded to go back on
+    NoHistory,
+    // no bookmark with this name
+    MissingBookmark(String),
+    // the node at path has no sibling on that side (the root has none at all)
+    NoSibling { path: Vec<K> },
+    // the node at path has no children
+    NoChildren { path: Vec<K> },
+}
+
+// One move of a navigator, as recorded in its log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Move<K> {
+    // went into this child
+    Into(K),
+    // got out of this child, to its parent
+    Out(K),
+    // jumped between two paths from the root
+    Jump { from: Vec<K>, to: Vec<K> },
+}
+
+// Paths show like filesystem ones, from the root: /a/b.
+impl <K: fmt::Display> fmt::Display for NavigationError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NavigationError::MissingChild { path, key } => write!(f, "no child {} under {}", key, display_path(path)),
+            NavigationError::AtRoot => write!(f, "already at the root"),
+            NavigationError::NoHistory => write!(f, "no previous move"),
+            NavigationError::MissingBookmark(name) => write!(f, "no bookmark {}", name),
+            NavigationError::NoSibling { path } => write!(f, "no sibling to {}", display_path(path)),
+            NavigationError::NoChildren { path } => write!(f, "no children under {}", display_path(path)),
+        }
+    }
+}
+
+fn display_path<K: fmt::Display>(path: &[K]) -> String {
+    let keys: Vec<String> = path.iter().map(|key| key.to_string()).collect();
+    format!("/{}", keys.join("/"))
+}
+
+impl <K: fmt::Debug + fmt::Display> std::error::Error for NavigationError<K> {}
+
+// factory for the values of the children created while auto-creating
+type MakeMissing<K, V> = Box<dyn FnMut(&K) -> V>;
+
+// Cursor over a tree it owns, moved around in place. The tree stays whole
+// while navigating, the cursor only remembering the keys leading to the
+// current node.
 pub struct TreeNavigator<K, V> {
-    previous: Vec<AddressableTree<K, V>>,
-    current: AddressableTree<K, V>,
+    root: AddressableTree<K, V>,
+    // keys from the root (excluded) down to the current node
+    path: Vec<K>,
+    // when set, going into a missing child creates it with a value from this
+    make_missing: Option<MakeMissing<K, V>>,
+    // every successful move so far, oldest first
+    moves: Vec<Move<K>>,
+    bookmarks: HashMap<String, Vec<K>>,
 }
 
 impl <K: Eq + core::hash::Hash + PartialEq + Clone, V> TreeNavigator<K, V> {
     pub fn new(tree: AddressableTree<K, V>) -> TreeNavigator<K, V> {
-        TreeNavigator {previous: Vec::new(), current: tree}
+        TreeNavigator {root: tree, path: Vec::new(), make_missing: None, moves: Vec::new(), bookmarks: HashMap::new()}
+    }
+
+    // Turns auto-creation (like `mkdir -p`) on, with the given factory for the
+    // values of missing children.
+    pub fn set_auto_create<F>(&mut self, make_missing: F)
+    where F: FnMut(&K) -> V + 'static {
+        self.make_missing = Some(Box::new(make_missing));
+    }
+
+    pub fn clear_auto_create(&mut self) {
+        self.make_missing = None;
     }
 
-    pub fn go_into(mut self, key: &mut K) -> Self {
-        let mut current = self.current;
-        let new_current = current.remove_child(key);
+    pub fn current(&self) -> &AddressableTree<K, V> {
+        self.root.get(&self.path).expect("navigator lost its current node")
+    }
 
-        self.previous.push(current);
-        self.current = new_current;
+    pub fn current_mut(&mut self) -> &mut AddressableTree<K, V> {
+        self.root.get_mut(&self.path).expect("navigator lost its current node")
+    }
 
-        return self;
+    pub fn root(&self) -> &AddressableTree<K, V> {
+        &self.root
     }
 
-    pub fn get_out(mut self) -> Self {
-        let current = self.current;
-        let mut new_current = self.previous.pop().unwrap();
+    // Hands the whole tree over for changes. Should the current node go away
+    // meanwhile, the navigator falls back to its closest remaining ancestor,
+    // which is logged as a jump like any other move.
+    pub fn edit_root<T, F>(&mut self, f: F) -> T
+    where F: FnOnce(&mut AddressableTree<K, V>) -> T {
+        let result = f(&mut self.root);
 
-        new_current.add_child_node(current.get_key().clone(), current);
+        let mut node = &self.root;
+        let mut depth = 0;
+        for key in &self.path {
+            match node.get(std::slice::from_ref(key)) {
+                Some(child) => node = child,
+                None => break,
+            }
+            depth += 1;
+        }
+
+        if depth < self.path.len() {
+            let to = self.path[..depth].to_vec();
+            self.jump_to(to);
+        }
 
-        self.current = new_current;
-        return self;
+        result
     }
 
-    pub fn apply_to_current<F>(mut self, f: F) -> Self
-    where F:FnOnce(AddressableTree<K, V>) -> AddressableTree<K, V> {
-        self.current = f(self.current);
-        return self;
+    // keys leading from the root (excluded) to the current node
+    pub fn current_path(&self) -> &[K] {
+        &self.path
     }
 
-    pub fn get(mut self) -> AddressableTree<K, V> {
+    // number of levels below the root, 0 at the root
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    // Failed moves leave the navigator where it was, and are not recorded.
 
-        // collapse all explored trees back into shape
-        while !self.previous.is_empty()
-        {
-            let mut previous_tree = self.previous.pop().unwrap();
+    pub fn go_into(&mut self, key: &K) -> Result<(), NavigationError<K>> {
+        self.enter(key)?;
+        self.moves.push(Move::Into(key.clone()));
+        Ok(())
+    }
 
-            previous_tree.add_child_node(self.current.get_key().clone(), self.current);
+    // Goes down the given path from the current node, in a single move.
+    pub fn go_into_path(&mut self, path: &[K]) -> Result<(), NavigationError<K>> {
+        let to = [self.path.as_slice(), path].concat();
+        let from = self.jump(&to)?;
+        self.moves.push(Move::Jump { from, to });
+        Ok(())
+    }
 
-            self.current = previous_tree;
+    pub fn get_out(&mut self) -> Result<(), NavigationError<K>> {
+        match self.path.pop() {
+            Some(key) => {
+                self.moves.push(Move::Out(key));
+                Ok(())
+            },
+            None => Err(NavigationError::AtRoot),
         }
-        
-        return self.current;
     }
-}
\ No newline at end of file
+
+    pub fn go_to_root(&mut self) {
+        let from = std::mem::take(&mut self.path);
+        self.moves.push(Move::Jump { from, to: Vec::new() });
+    }
+
+    // Goes to the node at the given path from the root. The error tells the
+    // first key along the path that is missing.
+    //
+    // Like go_into, both this and go_into_path create the missing nodes when
+    // auto-creating.
+    pub fn go_to(&mut self, path: &[K]) -> Result<(), NavigationError<K>> {
+        let from = self.jump(path)?;
+        self.moves.push(Move::Jump { from, to: path.to_vec() });
+        Ok(())
+    }
+
+    // -- ordered moves
+    //
+    // Siblings come in the child order of the tree (insertion or by key),
+    // which navigating doesn't change. Each of these is logged as a jump.
+
+    pub fn next_sibling(&mut self) -> Result<(), NavigationError<K>> {
+        let to = self.sibling_path(&self.path, 1)
+                     .ok_or_else(|| NavigationError::NoSibling { path: self.path.clone() })?;
+        self.jump_to(to);
+        Ok(())
+    }
+
+    pub fn prev_sibling(&mut self) -> Result<(), NavigationError<K>> {
+        let to = self.sibling_path(&self.path, -1)
+                     .ok_or_else(|| NavigationError::NoSibling { path: self.path.clone() })?;
+        self.jump_to(to);
+        Ok(())
+    }
+
+    pub fn first_child(&mut self) -> Result<(), NavigationError<K>> {
+        let key = self.current().get_children().first().map(|child| child.get_key().clone());
+        self.go_into_key(key)
+    }
+
+    pub fn last_child(&mut self) -> Result<(), NavigationError<K>> {
+        let key = self.current().get_children().last().map(|child| child.get_key().clone());
+        self.go_into_key(key)
+    }
+
+    // Steps to the next node in depth-first order (parents first): the first
+    // child, or else the next sibling of the closest node along the path that
+    // has one. Returns false, without moving, once the walk is over, so
+    // starting from the root and stepping until then visits the whole tree.
+    pub fn walk_next(&mut self) -> bool {
+        if self.first_child().is_ok() {
+            return true;
+        }
+
+        let next = (1..=self.path.len()).rev()
+                                        .find_map(|depth| self.sibling_path(&self.path[..depth], 1));
+        match next {
+            Some(to) => {
+                self.jump_to(to);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // -- history
+
+    // moves made so far, oldest first
+    pub fn moves(&self) -> &[Move<K>] {
+        &self.moves
+    }
+
+    pub fn clear_moves(&mut self) {
+        self.moves.clear();
+    }
+
+    // Goes back to where the navigator was before its last move, like `cd -`.
+    // This is a move of its own, so going back twice returns here.
+    pub fn back(&mut self) -> Result<(), NavigationError<K>> {
+        let previous = match self.moves.last() {
+            Some(Move::Into(_)) => self.path[..self.path.len() - 1].to_vec(),
+            Some(Move::Out(key)) => [self.path.as_slice(), std::slice::from_ref(key)].concat(),
+            Some(Move::Jump { from, .. }) => from.clone(),
+            None => return Err(NavigationError::NoHistory),
+        };
+
+        self.go_to(&previous)
+    }
+
+    // Reverts the last move, dropping it from the log. Returns the move undone.
+    pub fn undo(&mut self) -> Result<Move<K>, NavigationError<K>> {
+        let last = self.moves.pop().ok_or(NavigationError::NoHistory)?;
+
+        let reverted = match &last {
+            Move::Into(_) => {
+                self.path.pop();
+                Ok(())
+            },
+            Move::Out(key) => self.enter(key),
+            Move::Jump { from, .. } => self.jump(from).map(|_| ()),
+        };
+
+        // the tree may have changed since, leaving no way back
+        if let Err(error) = reverted {
+            self.moves.push(last);
+            return Err(error);
+        }
+
+        Ok(last)
+    }
+
+    // Makes the given moves again from where the navigator is, stopping at the
+    // first one that fails.
+    pub fn replay(&mut self, moves: &[Move<K>]) -> Result<(), NavigationError<K>> {
+        for m in moves {
+            match m {
+                Move::Into(key) => self.go_into(key)?,
+                Move::Out(_) => self.get_out()?,
+                Move::Jump { to, .. } => self.go_to(to)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    // -- bookmarks
+
+    // Remembers the current node under a name, replacing any bookmark with it.
+    pub fn set_bookmark(&mut self, name: &str) {
+        self.bookmarks.insert(name.to_string(), self.path.clone());
+    }
+
+    pub fn remove_bookmark(&mut self, name: &str) -> bool {
+        self.bookmarks.remove(name).is_some()
+    }
+
+    pub fn go_to_bookmark(&mut self, name: &str) -> Result<(), NavigationError<K>> {
+        let path = self.bookmarks.get(name)
+                                 .cloned()
+                                 .ok_or_else(|| NavigationError::MissingBookmark(name.to_string()))?;
+        self.go_to(&path)
+    }
+
+    fn go_into_key(&mut self, key: Option<K>) -> Result<(), NavigationError<K>> {
+        match key {
+            Some(key) => self.go_into(&key),
+            None => Err(NavigationError::NoChildren { path: self.path.clone() }),
+        }
+    }
+
+    // jumps to a path known to exist
+    fn jump_to(&mut self, to: Vec<K>) {
+        let from = std::mem::replace(&mut self.path, to.clone());
+        self.moves.push(Move::Jump { from, to });
+    }
+
+    // path of the sibling offset positions away from the node at path
+    fn sibling_path(&self, path: &[K], offset: isize) -> Option<Vec<K>> {
+        let (key, parent_path) = path.split_last()?;
+        let parent = self.root.get(parent_path)?;
+        let position = parent.position(key)?.checked_add_signed(offset)?;
+        let sibling = parent.child_at(position)?;
+
+        Some([parent_path, std::slice::from_ref(sibling.get_key())].concat())
+    }
+
+    // -- unrecorded moves
+
+    // goes into a child, creating it when auto-creating
+    fn enter(&mut self, key: &K) -> Result<(), NavigationError<K>> {
+        let current = self.root.get_mut(&self.path).expect("navigator lost its current node");
+
+        if current.get(std::slice::from_ref(key)).is_none() {
+            match self.make_missing.as_mut() {
+                Some(make) => current.add_child(key.clone(), make(key)),
+                None => return Err(NavigationError::MissingChild { path: self.path.clone(), key: key.clone() }),
+            }
+        }
+
+        self.path.push(key.clone());
+        Ok(())
+    }
+
+    // goes to a path from the root, returning the path left
+    fn jump(&mut self, path: &[K]) -> Result<Vec<K>, NavigationError<K>> {
+        let mut node = &mut self.root;
+
+        for (i, key) in path.iter().enumerate() {
+            if node.get(std::slice::from_ref(key)).is_none() {
+                match self.make_missing.as_mut() {
+                    Some(make) => node.add_child(key.clone(), make(key)),
+                    None => return Err(NavigationError::MissingChild { path: path[..i].to_vec(), key: key.clone() }),
+                }
+            }
+
+            node = node.get_mut(std::slice::from_ref(key)).expect("child was just checked");
+        }
+
+        Ok(std::mem::replace(&mut self.path, path.to_vec()))
+    }
+
+    pub fn get(self) -> AddressableTree<K, V> {
+        self.root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn navigator() -> TreeNavigator<&'static str, i32> {
+        let tree: AddressableTree<&str, i32> = [ (vec!["a", "b"], 2), (vec!["c"], 3) ].into_iter().collect();
+        TreeNavigator::new(tree)
+    }
+
+    #[test]
+    fn back_returns_before_the_last_move() {
+        let mut nav = navigator();
+        nav.go_into(&"a").unwrap();
+        nav.go_into(&"b").unwrap();
+
+        nav.back().unwrap();
+        assert_eq!(nav.current_path(), &["a"]);
+        nav.back().unwrap();
+        assert_eq!(nav.current_path(), &["a", "b"]);
+    }
+
+    #[test]
+    fn failed_moves_are_not_recorded() {
+        let mut nav = navigator();
+
+        assert_eq!(nav.go_into(&"x"), Err(NavigationError::MissingChild { path: Vec::new(), key: "x" }));
+        assert_eq!(nav.get_out(), Err(NavigationError::AtRoot));
+        assert_eq!(nav.back(), Err(NavigationError::NoHistory));
+        assert!(nav.moves().is_empty());
+    }
+
+    #[test]
+    fn losing_the_current_node_is_logged_as_a_jump() {
+        let mut nav = navigator();
+        nav.go_into(&"a").unwrap();
+        nav.go_into(&"b").unwrap();
+
+        nav.edit_root(|root| root.remove_at(&["a"]));
+        assert_eq!(nav.current_path(), &[] as &[&str]);
+        assert_eq!(nav.moves().last(), Some(&Move::Jump { from: vec!["a", "b"], to: Vec::new() }));
+
+        // the way back is gone, and stays in the log
+        assert_eq!(nav.back(), Err(NavigationError::MissingChild { path: Vec::new(), key: "a" }));
+        assert!(nav.undo().is_err());
+        assert_eq!(nav.moves().len(), 3);
+    }
+
+    #[test]
+    fn edits_keeping_the_current_node_are_not_moves() {
+        let mut nav = navigator();
+        nav.go_into(&"a").unwrap();
+
+        nav.edit_root(|root| root.remove_at(&["c"]));
+        assert_eq!(nav.current_path(), &["a"]);
+        assert_eq!(nav.moves(), &[Move::Into("a")]);
+
+        nav.undo().unwrap();
+        assert_eq!(nav.current_path(), &[] as &[&str]);
+    }
+
+    #[test]
+    fn errors_show_paths_from_the_root() {
+        let mut nav = navigator();
+        nav.go_into(&"a").unwrap();
+
+        assert_eq!(nav.go_into(&"x").unwrap_err().to_string(), "no child x under /a");
+        assert_eq!(NavigationError::MissingChild { path: Vec::new(), key: "a" }.to_string(), "no child a under /");
+        assert_eq!(NavigationError::NoSibling { path: vec!["b", "z"] }.to_string(), "no sibling to /b/z");
+        assert_eq!(NavigationError::<&str>::MissingBookmark("home".to_string()).to_string(), "no bookmark home");
+    }
+
+    #[test]
+    fn auto_create_builds_every_missing_node() {
+        let mut nav = navigator();
+        let mut made = 0;
+        nav.set_auto_create(move |key: &&str| {
+            made += 1;
+            key.len() as i32 * 10 + made
+        });
+
+        nav.go_into_path(&["a", "x", "yy"]).unwrap();
+        assert_eq!(nav.current_path(), &["a", "x", "yy"]);
+        nav.go_to(&["c", "zzz"]).unwrap();
+
+        let root = nav.get();
+        assert_eq!(root.get(&["a"]).map(|node| *node.get_value()), Some(0));
+        assert_eq!(root.get(&["a", "x"]).map(|node| *node.get_value()), Some(11));
+        assert_eq!(root.get(&["a", "x", "yy"]).map(|node| *node.get_value()), Some(22));
+        assert_eq!(root.get(&["c", "zzz"]).map(|node| *node.get_value()), Some(33));
+    }
+
+    #[test]
+    fn without_auto_create_the_first_missing_key_is_reported() {
+        let mut nav = navigator();
+        nav.set_auto_create(|_: &&str| 0);
+        nav.clear_auto_create();
+
+        assert_eq!(nav.go_into_path(&["a", "x", "y"]), Err(NavigationError::MissingChild { path: vec!["a"], key: "x" }));
+        assert_eq!(nav.go_to(&["q", "r"]), Err(NavigationError::MissingChild { path: Vec::new(), key: "q" }));
+        assert_eq!(nav.current_path(), &[] as &[&str]);
+        assert!(nav.root().get(&["a", "x"]).is_none());
+        assert!(nav.moves().is_empty());
+    }
+
+    #[test]
+    fn walk_visits_every_node_in_pre_order() {
+        let mut nav = navigator();
+
+        let mut visited = vec![nav.current_path().join("/")];
+        while nav.walk_next() {
+            visited.push(nav.current_path().join("/"));
+        }
+        assert_eq!(visited, ["", "a", "a/b", "c"]);
+
+        // the walk is over, and stays where it ended
+        let moves = nav.moves().len();
+        assert!(!nav.walk_next());
+        assert_eq!(nav.current_path(), &["c"]);
+        assert_eq!(nav.moves().len(), moves);
+    }
+
+    #[test]
+    fn siblings_follow_child_order() {
+        let mut nav = navigator();
+        nav.first_child().unwrap();
+        assert_eq!(nav.current_path(), &["a"]);
+
+        nav.next_sibling().unwrap();
+        assert_eq!(nav.current_path(), &["c"]);
+        nav.prev_sibling().unwrap();
+        assert_eq!(nav.current_path(), &["a"]);
+
+        nav.get_out().unwrap();
+        nav.last_child().unwrap();
+        assert_eq!(nav.current_path(), &["c"]);
+    }
+
+    #[test]
+    fn missing_siblings_and_children_are_errors() {
+        let mut nav = navigator();
+        assert_eq!(nav.next_sibling(), Err(NavigationError::NoSibling { path: Vec::new() }));
+        assert_eq!(nav.prev_sibling(), Err(NavigationError::NoSibling { path: Vec::new() }));
+
+        nav.go_into(&"a").unwrap();
+        assert_eq!(nav.prev_sibling(), Err(NavigationError::NoSibling { path: vec!["a"] }));
+
+        nav.go_to(&["c"]).unwrap();
+        assert_eq!(nav.next_sibling(), Err(NavigationError::NoSibling { path: vec!["c"] }));
+        assert_eq!(nav.first_child(), Err(NavigationError::NoChildren { path: vec!["c"] }));
+        assert_eq!(nav.last_child(), Err(NavigationError::NoChildren { path: vec!["c"] }));
+        assert_eq!(nav.current_path(), &["c"]);
+    }
+}
@@ -0,0 +1,221 @@
+use std::{num::NonZeroUsize, sync::{atomic::{AtomicUsize, Ordering}, Mutex}, thread};
+
+use crate::{addressable_tree::AddressableTree, tree_view::TreeView};
+
+// How work gets split across threads. The subtrees rooted split_depth levels
+// down (the root's children being at depth 1) are the units of work, shared
+// among at most max_threads threads, each taking the next unit once done with
+// one. The few nodes above them are handled afterwards, on the calling thread.
+#[derive(Clone, Copy)]
+pub struct Parallelism {
+    pub split_depth: usize,
+    pub max_threads: usize,
+}
+
+// as many threads as the machine can run at once
+impl Default for Parallelism {
+    fn default() -> Self {
+        Parallelism { split_depth: 2
+                    , max_threads: thread::available_parallelism().map_or(1, NonZeroUsize::get) }
+    }
+}
+
+impl <K, V> AddressableTree<K, V>
+where K: Eq + core::hash::Hash + PartialEq + Clone + Send + Sync, V: Send + Sync {
+    // Parallel counterpart of fold: every node is mapped on its own, and results
+    // are combined in post-order (children in order, then the node itself), so
+    // combine only needs to be associative.
+    pub fn par_fold<T, M, C>(&self, map: &M, combine: &C, parallelism: Parallelism) -> T
+    where T: Send, M: Fn(&AddressableTree<K, V>) -> T + Sync, C: Fn(T, T) -> T + Sync {
+        let mut units = Vec::new();
+        collect_units(self, parallelism.split_depth, &mut units);
+
+        let results = run_units(&units, parallelism.max_threads, |unit| fold_sequential(unit, map, combine));
+
+        fold_top(self, parallelism.split_depth, map, combine, &mut results.into_iter())
+    }
+
+    // Parallel counterpart of map_values.
+    pub fn par_map_values<F>(&mut self, f: &F, parallelism: Parallelism)
+    where F: Fn(&AddressableTree<K, V>) -> V + Sync {
+        {
+            let mut units = Vec::new();
+            collect_units_mut(self, parallelism.split_depth, &mut units);
+
+            // every unit is taken by a single thread, the lock only lets it through
+            let units: Vec<Mutex<&mut AddressableTree<K, V>>> = units.into_iter().map(Mutex::new).collect();
+            run_units(&units, parallelism.max_threads, |unit| unit.lock().expect("map thread panicked").map_values(f));
+        }
+
+        map_top(self, parallelism.split_depth, f);
+    }
+}
+
+// nodes depth levels down, in pre-order
+fn collect_units<'a, K, V>(tree: &'a AddressableTree<K, V>, depth: usize, units: &mut Vec<&'a AddressableTree<K, V>>)
+where K: Eq + core::hash::Hash + PartialEq + Clone {
+    if depth == 0 {
+        units.push(tree);
+        return;
+    }
+
+    for child in tree.get_children() {
+        collect_units(child, depth - 1, units);
+    }
+}
+
+fn collect_units_mut<'a, K, V>(tree: &'a mut AddressableTree<K, V>, depth: usize, units: &mut Vec<&'a mut AddressableTree<K, V>>)
+where K: Eq + core::hash::Hash + PartialEq + Clone {
+    if depth == 0 {
+        units.push(tree);
+        return;
+    }
+
+    for child in tree.children_mut() {
+        collect_units_mut(child, depth - 1, units);
+    }
+}
+
+// Does the work on every unit, on up to max_threads threads (none spawned for
+// a single one), and gives back the results in the order of the units.
+fn run_units<U, T, W>(units: &[U], max_threads: usize, work: W) -> Vec<T>
+where U: Sync, T: Send, W: Fn(&U) -> T + Sync {
+    let threads = max_threads.min(units.len());
+    if threads <= 1 {
+        return units.iter().map(work).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let (next, work) = (&next, &work);
+    let mut results: Vec<Option<T>> = units.iter().map(|_| None).collect();
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|_| scope.spawn(move || {
+                                              let mut done = Vec::new();
+                                              loop {
+                                                  let i = next.fetch_add(1, Ordering::Relaxed);
+                                                  match units.get(i) {
+                                                      Some(unit) => done.push((i, work(unit))),
+                                                      None => return done,
+                                                  }
+                                              }
+                                          }))
+                                          .collect();
+
+        for worker in workers {
+            for (i, result) in worker.join().expect("worker thread panicked") {
+                results[i] = Some(result);
+            }
+        }
+    });
+
+    results.into_iter().map(|result| result.expect("every unit was taken")).collect()
+}
+
+// plain fold in the same post-order
+fn fold_sequential<K, V, T, M, C>(tree: &AddressableTree<K, V>, map: &M, combine: &C) -> T
+where K: Eq + core::hash::Hash + PartialEq + Clone, M: Fn(&AddressableTree<K, V>) -> T, C: Fn(T, T) -> T {
+    tree.fold(&|node, acc: Option<T>| Some(match acc {
+                  Some(acc) => combine(acc, map(node)),
+                  None => map(node),
+              }), None)
+        .expect("fold visits at least the node itself")
+}
+
+// Combines the results of the units with the nodes above them, taking the
+// results in the order they were collected.
+fn fold_top<K, V, T, M, C>( tree: &AddressableTree<K, V>
+                          , depth: usize
+                          , map: &M
+                          , combine: &C
+                          , results: &mut impl Iterator<Item = T>
+                          ) -> T
+where K: Eq + core::hash::Hash + PartialEq + Clone, M: Fn(&AddressableTree<K, V>) -> T, C: Fn(T, T) -> T {
+    if depth == 0 {
+        return results.next().expect("one result per unit");
+    }
+
+    let children = tree.get_children()
+                       .into_iter()
+                       .map(|child| fold_top(child, depth - 1, map, combine, results))
+                       .reduce(combine);
+
+    let own = map(tree);
+    match children {
+        Some(acc) => combine(acc, own),
+        None => own,
+    }
+}
+
+// maps the values of the nodes above the units, children first
+fn map_top<K, V, F>(tree: &mut AddressableTree<K, V>, depth: usize, f: &F)
+where K: Eq + core::hash::Hash + PartialEq + Clone, F: Fn(&AddressableTree<K, V>) -> V {
+    if depth == 0 {
+        return;
+    }
+
+    for child in tree.children_mut() {
+        map_top(child, depth - 1, f);
+    }
+
+    let value = f(tree);
+    tree.set_value(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // three levels of three children, plus a leaf right under the root
+    fn tree() -> AddressableTree<u32, u64> {
+        let mut tree = AddressableTree::singleton(0, 1);
+        for i in 1..=3 {
+            let mut child = AddressableTree::singleton(i, i as u64);
+            for j in 1..=3 {
+                let mut grandchild = AddressableTree::singleton(j, (10 * i + j) as u64);
+                for k in 1..=3 {
+                    grandchild.add_child(k, (100 * i + 10 * j + k) as u64);
+                }
+                child.add_child_node(j, grandchild);
+            }
+            tree.add_child_node(i, child);
+        }
+        tree.add_child(4, 4);
+        tree
+    }
+
+    fn value(node: &AddressableTree<u32, u64>) -> Vec<u64> {
+        vec![*node.get_value()]
+    }
+
+    #[test]
+    fn par_fold_keeps_post_order_whatever_the_split() {
+        let tree = tree();
+        let expected = tree.fold(&|node, mut acc: Vec<u64>| { acc.push(*node.get_value()); acc }, Vec::new());
+
+        for split_depth in 0..=4 {
+            for max_threads in [1, 2, 4, 64] {
+                let parallelism = Parallelism { split_depth, max_threads };
+                let folded = tree.par_fold(&value, &|mut a, b| { a.extend(b); a }, parallelism);
+
+                assert_eq!(folded, expected, "split_depth {} max_threads {}", split_depth, max_threads);
+            }
+        }
+    }
+
+    #[test]
+    fn par_map_values_matches_map_values() {
+        let subtree_sum = |node: &AddressableTree<u32, u64>| node.fold(&|node, acc| acc + node.get_value(), 0);
+        let mut expected = tree();
+        expected.map_values(subtree_sum);
+
+        for split_depth in 0..=4 {
+            for max_threads in [1, 3] {
+                let mut tree = tree();
+                tree.par_map_values(&subtree_sum, Parallelism { split_depth, max_threads });
+
+                assert_eq!(tree, expected, "split_depth {} max_threads {}", split_depth, max_threads);
+            }
+        }
+    }
+}
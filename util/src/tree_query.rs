@@ -0,0 +1,192 @@
+use std::collections::HashSet;
+
+use crate::addressable_tree::AddressableTree;
+
+// One segment of a path pattern, matched against one level of keys (or, for
+// AnyDepth, any number of levels).
+pub enum Segment<K> {
+    // exactly this key
+    Key(K),
+    // any key, like `*`
+    Any,
+    // any number of levels, none included, like `**`
+    AnyDepth,
+    // any key accepted by the predicate
+    Matches(Box<dyn Fn(&K) -> bool>),
+}
+
+impl Segment<String> {
+    // Parses a glob like `**/d/*.log`: segments are separated by `/`, `**`
+    // spans any number of levels, and `*` and `?` inside a segment match any
+    // run of characters and any single character.
+    pub fn parse_glob(pattern: &str) -> Vec<Segment<String>> {
+        pattern.split('/')
+               .filter(|segment| !segment.is_empty())
+               .map(|segment| match segment {
+                   "**" => Segment::AnyDepth,
+                   "*" => Segment::Any,
+                   glob if glob.contains(['*', '?']) => {
+                       let glob = glob.to_string();
+                       Segment::Matches(Box::new(move |key: &String| glob_match(&glob, key)))
+                   },
+                   key => Segment::Key(key.to_string()),
+               })
+               .collect()
+    }
+}
+
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V> AddressableTree<K, V> {
+    // Nodes whose path (from this node, excluded) matches the pattern, each
+    // found once. The empty pattern matches this node itself.
+    pub fn find(&self, pattern: &[Segment<K>]) -> Vec<(Vec<K>, &AddressableTree<K, V>)> {
+        let mut found = Vec::new();
+        let mut visited = HashSet::new();
+        let mut path = Vec::new();
+
+        find_from(self, pattern, 0, &mut path, &mut visited, &mut found);
+
+        found
+    }
+}
+
+fn find_from<'a, K, V>( node: &'a AddressableTree<K, V>
+                      , pattern: &[Segment<K>]
+                      , i: usize
+                      , path: &mut Vec<K>
+                      , visited: &mut HashSet<(*const AddressableTree<K, V>, usize)>
+                      , found: &mut Vec<(Vec<K>, &'a AddressableTree<K, V>)>
+                      )
+where K: Eq + core::hash::Hash + PartialEq + Clone {
+    // `**` can reach the same node at the same point of the pattern in many ways
+    if !visited.insert((node as *const _, i)) {
+        return;
+    }
+
+    let Some(segment) = pattern.get(i) else {
+        found.push((path.clone(), node));
+        return;
+    };
+
+    if let Segment::AnyDepth = segment {
+        // matching no level at all
+        find_from(node, pattern, i + 1, path, visited, found);
+    }
+
+    for child in node.get_children() {
+        let key = child.get_key();
+
+        let next = match segment {
+            Segment::Key(k) if k == key => i + 1,
+            Segment::Any => i + 1,
+            Segment::Matches(predicate) if predicate(key) => i + 1,
+            // matching one more level, and maybe more
+            Segment::AnyDepth => i,
+            _ => continue,
+        };
+
+        path.push(key.clone());
+        find_from(child, pattern, next, path, visited, found);
+        path.pop();
+    }
+}
+
+// `*` matches any run of characters, `?` any single one
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // last `*` seen, and where in the text it started matching
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // let the last `*` eat one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tree_view::TreeView;
+
+    use super::*;
+
+    // /a/x.log, /a/d/y.log, /a/d/z.txt, /b.log
+    fn sample() -> AddressableTree<String, i32> {
+        [ (vec!["a", "x.log"], 1), (vec!["a", "d", "y.log"], 2), (vec!["a", "d", "z.txt"], 3), (vec!["b.log"], 4) ]
+            .into_iter()
+            .map(|(path, value)| (path.into_iter().map(String::from).collect(), value))
+            .collect()
+    }
+
+    fn found(tree: &AddressableTree<String, i32>, pattern: &[Segment<String>]) -> Vec<String> {
+        tree.find(pattern).into_iter().map(|(path, _)| path.join("/")).collect()
+    }
+
+    #[test]
+    fn globs_match_runs_and_single_characters() {
+        assert!(glob_match("*.log", "x.log"));
+        assert!(glob_match("*.log", ".log"));
+        assert!(!glob_match("*.log", "x.log.gz"));
+        assert!(glob_match("?b", "ab"));
+        assert!(!glob_match("?b", "b"));
+        assert!(!glob_match("?b", "abb"));
+        assert!(glob_match("a*", "a"));
+        assert!(glob_match("a*", "abc"));
+        assert!(!glob_match("a*", "ba"));
+        // backtracking over a `*` that ate too little
+        assert!(glob_match("*a*b", "xaxab"));
+        assert!(!glob_match("*a*b", "xaxa"));
+    }
+
+    #[test]
+    fn any_depth_can_match_no_level() {
+        // matches at the first level come first, the zero-level match being tried first
+        assert_eq!(found(&sample(), &Segment::parse_glob("**/*.log")), ["b.log", "a/x.log", "a/d/y.log"]);
+    }
+
+    #[test]
+    fn trailing_any_depth_includes_the_node_itself() {
+        assert_eq!(found(&sample(), &Segment::parse_glob("a/d/**")), ["a/d", "a/d/y.log", "a/d/z.txt"]);
+    }
+
+    #[test]
+    fn any_depth_alone_finds_every_node_once() {
+        let tree = sample();
+
+        assert_eq!(found(&tree, &Segment::parse_glob("**")), ["", "a", "a/x.log", "a/d", "a/d/y.log", "a/d/z.txt", "b.log"]);
+        // many ways to reach the same nodes
+        assert_eq!(found(&tree, &Segment::parse_glob("**/**/**")).len(), tree.len() + 1);
+    }
+
+    #[test]
+    fn keys_and_any_match_one_level() {
+        let tree = sample();
+
+        assert_eq!(found(&tree, &Segment::parse_glob("a/*")), ["a/x.log", "a/d"]);
+        assert_eq!(found(&tree, &Segment::parse_glob("/a/d/")), ["a/d"]);
+        assert_eq!(found(&tree, &[]), [""]);
+        assert!(found(&tree, &Segment::parse_glob("a/q")).is_empty());
+    }
+
+    #[test]
+    fn predicates_select_keys() {
+        let pattern = [Segment::AnyDepth, Segment::Matches(Box::new(|key: &String| key.starts_with(['x', 'z'])))];
+
+        assert_eq!(found(&sample(), &pattern), ["a/x.log", "a/d/z.txt"]);
+    }
+}
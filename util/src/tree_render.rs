@@ -0,0 +1,187 @@
+use std::fmt;
+
+use crate::addressable_tree::AddressableTree;
+
+// Label of a node, given its key and value.
+pub type Format<K, V> = Box<dyn Fn(&K, &V) -> String>;
+
+pub struct RenderOptions<K, V> {
+    // label of each node
+    pub format: Format<K, V>,
+    // deepest level shown, the root being at depth 0
+    pub max_depth: Option<usize>,
+    // only show nodes with children (the root is always shown)
+    pub hide_leaves: bool,
+}
+
+impl <K, V> RenderOptions<K, V> {
+    pub fn new<F>(format: F) -> RenderOptions<K, V>
+    where F: Fn(&K, &V) -> String + 'static {
+        RenderOptions { format: Box::new(format), max_depth: None, hide_leaves: false }
+    }
+}
+
+impl <K: fmt::Display, V: fmt::Debug> Default for RenderOptions<K, V> {
+    fn default() -> Self {
+        RenderOptions::new(|key, value| format!("{} {:?}", key, value))
+    }
+}
+
+// Display of a tree like the `tree` command does:
+//
+// /
+// ├── a
+// │   └── e
+// └── d
+pub struct TreeRender<'a, K, V> {
+    tree: &'a AddressableTree<K, V>,
+    options: &'a RenderOptions<K, V>,
+}
+
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V> AddressableTree<K, V> {
+    pub fn render<'a>(&'a self, options: &'a RenderOptions<K, V>) -> TreeRender<'a, K, V> {
+        TreeRender { tree: self, options }
+    }
+
+    // Graphviz DOT graph of the tree, render it with e.g. `dot -Tsvg`
+    pub fn to_dot(&self, options: &RenderOptions<K, V>) -> String {
+        let mut dot = String::from("digraph tree {\n    node [shape=box];\n");
+        let mut next_id = 0;
+
+        dot_node(self, options, 0, &mut next_id, &mut dot);
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl <K: Eq + core::hash::Hash + PartialEq + Clone, V> fmt::Display for TreeRender<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", (self.options.format)(self.tree.get_key(), self.tree.get_value()))?;
+        render_children(self.tree, self.options, 1, "", f)
+    }
+}
+
+fn visible_children<'a, K, V>( tree: &'a AddressableTree<K, V>
+                             , options: &RenderOptions<K, V>
+                             , depth: usize
+                             ) -> Vec<&'a AddressableTree<K, V>>
+where K: Eq + core::hash::Hash + PartialEq + Clone {
+    if options.max_depth.is_some_and(|max_depth| depth > max_depth) {
+        return Vec::new();
+    }
+
+    tree.get_children()
+        .into_iter()
+        .filter(|child| !options.hide_leaves || !child.get_children().is_empty())
+        .collect()
+}
+
+fn render_children<K, V>( tree: &AddressableTree<K, V>
+                        , options: &RenderOptions<K, V>
+                        , depth: usize
+                        , prefix: &str
+                        , f: &mut fmt::Formatter<'_>
+                        ) -> fmt::Result
+where K: Eq + core::hash::Hash + PartialEq + Clone {
+    let children = visible_children(tree, options, depth);
+    let last = children.len().saturating_sub(1);
+
+    for (i, child) in children.into_iter().enumerate() {
+        let (branch, indent) = if i == last { ("└── ", "    ") } else { ("├── ", "│   ") };
+
+        writeln!(f, "{}{}{}", prefix, branch, (options.format)(child.get_key(), child.get_value()))?;
+        render_children(child, options, depth + 1, &format!("{}{}", prefix, indent), f)?;
+    }
+
+    Ok(())
+}
+
+fn dot_node<K, V>( tree: &AddressableTree<K, V>
+                 , options: &RenderOptions<K, V>
+                 , depth: usize
+                 , next_id: &mut usize
+                 , dot: &mut String
+                 ) -> usize
+where K: Eq + core::hash::Hash + PartialEq + Clone {
+    let id = *next_id;
+    *next_id += 1;
+
+    let label = (options.format)(tree.get_key(), tree.get_value());
+    dot.push_str(&format!("    n{} [label=\"{}\"];\n", id, escape(&label)));
+
+    for child in visible_children(tree, options, depth + 1) {
+        let child_id = dot_node(child, options, depth + 1, next_id, dot);
+        dot.push_str(&format!("    n{} -> n{};\n", id, child_id));
+    }
+
+    id
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // root (0) holding a(1), which holds b(2) and c(3), which holds d(4), then e(5)
+    fn sample() -> AddressableTree<&'static str, i32> {
+        [ (vec!["a"], 1), (vec!["a", "b"], 2), (vec!["a", "c"], 3), (vec!["a", "c", "d"], 4), (vec!["e"], 5) ].into_iter().collect()
+    }
+
+    fn labelled(unit: &'static str) -> RenderOptions<&'static str, i32> {
+        RenderOptions::new(move |key: &&str, value: &i32| format!("{} ({}{})", if key.is_empty() { "/" } else { key }, value, unit))
+    }
+
+    #[test]
+    fn render_draws_branches() {
+        assert_eq!(sample().render(&labelled("B")).to_string(), "\
+/ (0B)
+├── a (1B)
+│   ├── b (2B)
+│   └── c (3B)
+│       └── d (4B)
+└── e (5B)
+");
+    }
+
+    #[test]
+    fn render_stops_at_max_depth() {
+        let options = RenderOptions { max_depth: Some(1), ..labelled("") };
+
+        assert_eq!(sample().render(&options).to_string(), "\
+/ (0)
+├── a (1)
+└── e (5)
+");
+    }
+
+    #[test]
+    fn render_hides_leaves() {
+        let options = RenderOptions { hide_leaves: true, ..labelled("") };
+
+        assert_eq!(sample().render(&options).to_string(), "\
+/ (0)
+└── a (1)
+    └── c (3)
+");
+    }
+
+    #[test]
+    fn dot_escapes_labels() {
+        let mut tree = AddressableTree::singleton("r", "say \"hi\"");
+        tree.add_child("c", "back\\slash\nnewline");
+
+        let options = RenderOptions::new(|_: &&str, value: &&str| value.to_string());
+        assert_eq!(tree.to_dot(&options), "\
+digraph tree {
+    node [shape=box];
+    n0 [label=\"say \\\"hi\\\"\"];
+    n1 [label=\"back\\\\slash\\nnewline\"];
+    n0 -> n1;
+}
+");
+    }
+}
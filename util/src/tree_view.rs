@@ -0,0 +1,212 @@
+use std::{collections::VecDeque, ops::ControlFlow};
+
+// Read-only queries (lookups, sizes, folds, iterators) shared by the trees
+// owning their children in order, written once over the key, value and
+// children of a node.
+//
+// Like AddressableTree, the root is the anchor of the tree: an empty tree is
+// one whose root has no children, and the sizes only count what is below it.
+pub trait TreeView: Sized {
+    type Key: PartialEq + Clone;
+    type Value;
+
+    fn key(&self) -> &Self::Key;
+
+    fn value(&self) -> &Self::Value;
+
+    // children in order
+    fn children(&self) -> &[Self];
+
+    // child with the given key, trees indexing their children can do better
+    // than this scan
+    fn child(&self, key: &Self::Key) -> Option<&Self> {
+        self.children().iter().find(|child| child.key() == key)
+    }
+
+    // Paths are keys leading from this node (excluded) down to the target node,
+    // the empty path being this node itself.
+
+    fn get(&self, path: &[Self::Key]) -> Option<&Self> {
+        let mut node = self;
+        for key in path {
+            node = node.child(key)?;
+        }
+        Some(node)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.children().is_empty()
+    }
+
+    // number of nodes below the root
+    fn len(&self) -> usize {
+        self.children().iter().map(|child| 1 + child.len()).sum()
+    }
+
+    // number of levels below the root
+    fn depth(&self) -> usize {
+        self.children().iter().map(|child| 1 + child.depth()).max().unwrap_or(0)
+    }
+
+    // number of nodes below the root without children
+    fn leaf_count(&self) -> usize {
+        self.children().iter()
+                       .map(|child| if child.is_empty() { 1 } else { child.leaf_count() })
+                       .sum()
+    }
+
+    // Folds the nodes in post-order: children (in order) before their parent.
+    fn fold<T>(&self, f: &impl Fn(&Self, T) -> T, initial: T) -> T {
+        let mut acc = initial;
+
+        for child in self.children() {
+            acc = child.fold(f, acc);
+        }
+
+        f(self, acc)
+    }
+
+    // same as fold, but visiting parents before their children
+    fn fold_pre_order<T>(&self, f: &impl Fn(&Self, T) -> T, initial: T) -> T {
+        let mut acc = f(self, initial);
+
+        for child in self.children() {
+            acc = child.fold_pre_order(f, acc);
+        }
+
+        acc
+    }
+
+    // Same as fold, also giving each node's depth and the path of keys leading
+    // to it from this node (depth 0, empty path).
+    fn fold_with_context<T>(&self, f: &impl Fn(&Self, usize, &[Self::Key], T) -> T, initial: T) -> T {
+        let mut path = Vec::new();
+        fold_with_path(self, f, &mut path, initial)
+    }
+
+    // Same as fold, stopping at the first Err, which is returned.
+    fn try_fold<T, E>(&self, f: &impl Fn(&Self, T) -> Result<T, E>, initial: T) -> Result<T, E> {
+        let mut acc = initial;
+
+        for child in self.children() {
+            acc = child.try_fold(f, acc)?;
+        }
+
+        f(self, acc)
+    }
+
+    // Same as fold, stopping at the first Break, which is returned.
+    fn fold_until<T, B>(&self, f: &impl Fn(&Self, T) -> ControlFlow<B, T>, initial: T) -> ControlFlow<B, T> {
+        let result = self.try_fold(&|node, acc| match f(node, acc) {
+            ControlFlow::Continue(acc) => Ok(acc),
+            ControlFlow::Break(b) => Err(b),
+        }, initial);
+
+        match result {
+            Ok(acc) => ControlFlow::Continue(acc),
+            Err(b) => ControlFlow::Break(b),
+        }
+    }
+
+    // Iterators yield each node along with the path of keys leading to it,
+    // starting from (and excluding) this node, whose path is empty.
+
+    fn pre_order(&self) -> PreOrder<'_, Self> {
+        PreOrder { stack: vec![(Vec::new(), self)] }
+    }
+
+    fn post_order(&self) -> PostOrder<'_, Self> {
+        PostOrder { stack: vec![(Vec::new(), self, false)] }
+    }
+
+    fn level_order(&self) -> LevelOrder<'_, Self> {
+        LevelOrder { queue: VecDeque::from([(Vec::new(), self)]) }
+    }
+}
+
+fn fold_with_path<N, T>( node: &N
+                       , f: &impl Fn(&N, usize, &[N::Key], T) -> T
+                       , path: &mut Vec<N::Key>
+                       , initial: T
+                       ) -> T
+where N: TreeView {
+    let mut acc = initial;
+
+    for child in node.children() {
+        path.push(child.key().clone());
+        acc = fold_with_path(child, f, path, acc);
+        path.pop();
+    }
+
+    f(node, path.len(), path, acc)
+}
+
+pub(crate) fn child_path<K: Clone>(path: &[K], key: &K) -> Vec<K> {
+    let mut child_path = path.to_vec();
+    child_path.push(key.clone());
+    child_path
+}
+
+pub struct PreOrder<'a, N: TreeView> {
+    stack: Vec<(Vec<N::Key>, &'a N)>,
+}
+
+impl <'a, N: TreeView> Iterator for PreOrder<'a, N> {
+    type Item = (Vec<N::Key>, &'a N);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, node) = self.stack.pop()?;
+
+        // reversed, so children come out in order
+        for child in node.children().iter().rev() {
+            self.stack.push((child_path(&path, child.key()), child));
+        }
+
+        Some((path, node))
+    }
+}
+
+pub struct PostOrder<'a, N: TreeView> {
+    // the flag marks nodes whose children were already pushed
+    stack: Vec<(Vec<N::Key>, &'a N, bool)>,
+}
+
+impl <'a, N: TreeView> Iterator for PostOrder<'a, N> {
+    type Item = (Vec<N::Key>, &'a N);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (path, node, expanded) = self.stack.pop()?;
+
+            if expanded || node.is_empty() {
+                return Some((path, node));
+            }
+
+            let children: Vec<_> = node.children()
+                                       .iter()
+                                       .rev()
+                                       .map(|child| (child_path(&path, child.key()), child, false))
+                                       .collect();
+            self.stack.push((path, node, true));
+            self.stack.extend(children);
+        }
+    }
+}
+
+pub struct LevelOrder<'a, N: TreeView> {
+    queue: VecDeque<(Vec<N::Key>, &'a N)>,
+}
+
+impl <'a, N: TreeView> Iterator for LevelOrder<'a, N> {
+    type Item = (Vec<N::Key>, &'a N);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, node) = self.queue.pop_front()?;
+
+        for child in node.children() {
+            self.queue.push_back((child_path(&path, child.key()), child));
+        }
+
+        Some((path, node))
+    }
+}